@@ -0,0 +1,3 @@
+-- The baseline may predate migrations on hand-provisioned databases, so reverting it
+-- leaves the tables in place rather than dropping their data.
+SELECT 1;
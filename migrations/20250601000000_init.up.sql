@@ -0,0 +1,21 @@
+-- Baseline schema the application was first deployed with. Databases provisioned by hand
+-- before migrations existed already have these tables, so every statement is a no-op there.
+CREATE TABLE IF NOT EXISTS links (
+    id SERIAL PRIMARY KEY,
+    slug VARCHAR(32) NOT NULL,
+    target_url TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    CONSTRAINT links_slug_key UNIQUE (slug)
+);
+
+CREATE TABLE IF NOT EXISTS clicks (
+    id BIGSERIAL PRIMARY KEY,
+    slug VARCHAR(32) NOT NULL,
+    ip TEXT NOT NULL,
+    user_agent TEXT,
+    referer TEXT,
+    timestamp TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_clicks_slug ON clicks (slug);
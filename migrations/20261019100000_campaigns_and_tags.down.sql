@@ -0,0 +1,5 @@
+ALTER TABLE links
+    DROP COLUMN IF EXISTS campaign_id,
+    DROP COLUMN IF EXISTS tags;
+
+DROP TABLE IF EXISTS campaigns;
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS campaigns (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT campaigns_name_key UNIQUE (name)
+);
+
+ALTER TABLE links
+    ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS campaign_id INTEGER REFERENCES campaigns (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_links_tags ON links USING GIN (tags);
+CREATE INDEX IF NOT EXISTS idx_links_campaign_id ON links (campaign_id);
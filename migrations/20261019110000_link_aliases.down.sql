@@ -0,0 +1,3 @@
+ALTER TABLE clicks DROP COLUMN IF EXISTS alias;
+
+DROP TABLE IF EXISTS link_aliases;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS link_aliases (
+    alias VARCHAR(32) PRIMARY KEY,
+    slug VARCHAR(32) NOT NULL REFERENCES links (slug) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_link_aliases_slug ON link_aliases (slug);
+
+-- Clicks are stored under the primary slug; this records which alias was followed
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS alias VARCHAR(32);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS link_revisions;
+DROP FUNCTION IF EXISTS link_revisions_append_only();
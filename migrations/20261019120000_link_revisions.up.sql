@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS link_revisions (
+    id BIGSERIAL PRIMARY KEY,
+    slug VARCHAR(32) NOT NULL,
+    revision INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    target_url TEXT NOT NULL,
+    expires_at TIMESTAMPTZ,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    campaign_id INTEGER,
+    source_revision INTEGER,
+    actor TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT link_revisions_slug_revision_key UNIQUE (slug, revision)
+);
+
+-- History is append-only
+CREATE OR REPLACE FUNCTION link_revisions_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'link_revisions is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS link_revisions_append_only ON link_revisions;
+CREATE TRIGGER link_revisions_append_only
+    BEFORE UPDATE OR DELETE ON link_revisions
+    FOR EACH ROW EXECUTE FUNCTION link_revisions_append_only();
+
+-- Seed the history with the current state of existing links
+INSERT INTO link_revisions (slug, revision, action, target_url, expires_at, tags, campaign_id, actor, created_at)
+SELECT slug, 1, 'created', target_url, expires_at, tags, campaign_id, 'unknown', created_at
+FROM links
+ON CONFLICT (slug, revision) DO NOTHING;
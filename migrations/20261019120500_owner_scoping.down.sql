@@ -0,0 +1,12 @@
+TRUNCATE idempotency_keys;
+
+ALTER TABLE idempotency_keys DROP CONSTRAINT IF EXISTS idempotency_keys_pkey;
+ALTER TABLE idempotency_keys ADD CONSTRAINT idempotency_keys_pkey PRIMARY KEY (key);
+ALTER TABLE idempotency_keys DROP COLUMN IF EXISTS owner;
+
+ALTER TABLE campaigns DROP CONSTRAINT IF EXISTS campaigns_owner_name_key;
+ALTER TABLE campaigns ADD CONSTRAINT campaigns_name_key UNIQUE (name);
+ALTER TABLE campaigns DROP COLUMN IF EXISTS owner;
+
+DROP INDEX IF EXISTS idx_links_owner;
+ALTER TABLE links DROP COLUMN IF EXISTS owner;
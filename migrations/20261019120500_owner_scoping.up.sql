@@ -0,0 +1,17 @@
+-- Links and campaigns belong to the actor that created them (see Actor)
+ALTER TABLE links ADD COLUMN IF NOT EXISTS owner TEXT NOT NULL DEFAULT 'anonymous';
+CREATE INDEX IF NOT EXISTS idx_links_owner ON links (owner);
+
+ALTER TABLE campaigns ADD COLUMN IF NOT EXISTS owner TEXT NOT NULL DEFAULT 'anonymous';
+
+-- Campaign names only need to be unique per owner
+ALTER TABLE campaigns DROP CONSTRAINT IF EXISTS campaigns_name_key;
+ALTER TABLE campaigns ADD CONSTRAINT campaigns_owner_name_key UNIQUE (owner, name);
+
+-- Stored keys predate owner scoping and their fingerprints no longer match, so they are dropped
+TRUNCATE idempotency_keys;
+
+-- Keys are scoped to the caller, so two API keys can use the same Idempotency-Key
+ALTER TABLE idempotency_keys ADD COLUMN IF NOT EXISTS owner TEXT NOT NULL DEFAULT 'anonymous';
+ALTER TABLE idempotency_keys DROP CONSTRAINT IF EXISTS idempotency_keys_pkey;
+ALTER TABLE idempotency_keys ADD CONSTRAINT idempotency_keys_pkey PRIMARY KEY (owner, key);
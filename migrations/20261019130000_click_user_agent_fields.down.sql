@@ -0,0 +1,5 @@
+ALTER TABLE clicks
+    DROP COLUMN IF EXISTS device_type,
+    DROP COLUMN IF EXISTS os_family,
+    DROP COLUMN IF EXISTS browser_version,
+    DROP COLUMN IF EXISTS browser_family;
@@ -0,0 +1,5 @@
+ALTER TABLE clicks
+    ADD COLUMN IF NOT EXISTS browser_family TEXT,
+    ADD COLUMN IF NOT EXISTS browser_version TEXT,
+    ADD COLUMN IF NOT EXISTS os_family TEXT,
+    ADD COLUMN IF NOT EXISTS device_type TEXT;
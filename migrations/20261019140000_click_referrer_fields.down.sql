@@ -0,0 +1,3 @@
+ALTER TABLE clicks
+    DROP COLUMN IF EXISTS channel,
+    DROP COLUMN IF EXISTS referrer_domain;
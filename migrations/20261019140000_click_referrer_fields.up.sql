@@ -0,0 +1,9 @@
+ALTER TABLE clicks
+    ADD COLUMN IF NOT EXISTS referrer_domain TEXT,
+    ADD COLUMN IF NOT EXISTS channel TEXT;
+
+-- Best-effort backfill; rows ingested from now on are classified by the consumer
+UPDATE clicks
+SET referrer_domain = lower(substring(referer from '^[A-Za-z][A-Za-z0-9+.-]*://(?:www\.)?([^/:?#]+)')),
+    channel = CASE WHEN referer IS NULL OR referer = '' THEN 'direct' ELSE 'other' END
+WHERE channel IS NULL;
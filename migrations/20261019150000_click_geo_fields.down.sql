@@ -0,0 +1,6 @@
+ALTER TABLE clicks
+    DROP COLUMN IF EXISTS asn_org,
+    DROP COLUMN IF EXISTS asn,
+    DROP COLUMN IF EXISTS city,
+    DROP COLUMN IF EXISTS region,
+    DROP COLUMN IF EXISTS country;
@@ -0,0 +1,6 @@
+ALTER TABLE clicks
+    ADD COLUMN IF NOT EXISTS country VARCHAR(2),
+    ADD COLUMN IF NOT EXISTS region TEXT,
+    ADD COLUMN IF NOT EXISTS city TEXT,
+    ADD COLUMN IF NOT EXISTS asn BIGINT,
+    ADD COLUMN IF NOT EXISTS asn_org TEXT;
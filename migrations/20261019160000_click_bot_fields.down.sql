@@ -0,0 +1,4 @@
+ALTER TABLE clicks
+    DROP COLUMN IF EXISTS bot_name,
+    DROP COLUMN IF EXISTS bot_reason,
+    DROP COLUMN IF EXISTS is_bot;
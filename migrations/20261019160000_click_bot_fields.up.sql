@@ -0,0 +1,9 @@
+ALTER TABLE clicks
+    ADD COLUMN IF NOT EXISTS is_bot BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN IF NOT EXISTS bot_reason TEXT,
+    ADD COLUMN IF NOT EXISTS bot_name TEXT;
+
+-- Best-effort backfill from the parsed device type; new rows are classified by the consumer
+UPDATE clicks
+SET is_bot = true, bot_reason = 'crawler', bot_name = browser_family
+WHERE device_type = 'bot' AND NOT is_bot;
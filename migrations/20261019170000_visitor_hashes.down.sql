@@ -0,0 +1,5 @@
+ALTER TABLE clicks DROP COLUMN IF EXISTS visitor_id;
+UPDATE clicks SET ip = 'Unknown' WHERE ip IS NULL;
+ALTER TABLE clicks ALTER COLUMN ip SET NOT NULL;
+
+DROP TABLE IF EXISTS visitor_salts;
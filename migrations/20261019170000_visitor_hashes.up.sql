@@ -0,0 +1,10 @@
+-- Daily salts for visitor hashes; old salts are deleted so hashes cannot be recomputed
+CREATE TABLE IF NOT EXISTS visitor_salts (
+    day DATE PRIMARY KEY,
+    salt TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Hashed IP storage leaves the raw address empty
+ALTER TABLE clicks ALTER COLUMN ip DROP NOT NULL;
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS visitor_id TEXT;
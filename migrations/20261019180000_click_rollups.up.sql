@@ -0,0 +1,41 @@
+-- Click counts per slug, bucket and dimension value. Country and bot flag are part of the key
+-- so the analytics filters apply to rollups the same way they apply to raw clicks.
+-- Missing countries are stored as '' because primary key columns cannot be NULL.
+CREATE TABLE IF NOT EXISTS click_rollups_hourly (
+    slug VARCHAR(32) NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    dimension TEXT NOT NULL,
+    value TEXT NOT NULL,
+    country VARCHAR(2) NOT NULL DEFAULT '',
+    is_bot BOOLEAN NOT NULL DEFAULT false,
+    count BIGINT NOT NULL,
+    PRIMARY KEY (slug, timestamp, dimension, value, country, is_bot)
+);
+
+CREATE TABLE IF NOT EXISTS click_rollups_daily (
+    slug VARCHAR(32) NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    dimension TEXT NOT NULL,
+    value TEXT NOT NULL,
+    country VARCHAR(2) NOT NULL DEFAULT '',
+    is_bot BOOLEAN NOT NULL DEFAULT false,
+    count BIGINT NOT NULL,
+    PRIMARY KEY (slug, timestamp, dimension, value, country, is_bot)
+);
+
+-- Rollups advance by ingestion time rather than by clicks.id, since ids are assigned at insert
+-- and a click can commit behind a higher id. Existing clicks get the epoch (a constant default,
+-- so no table rewrite) and are folded first.
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS ingested_at TIMESTAMPTZ NOT NULL DEFAULT 'epoch';
+ALTER TABLE clicks ALTER COLUMN ingested_at SET DEFAULT NOW();
+CREATE INDEX IF NOT EXISTS idx_clicks_ingested_at ON clicks (ingested_at, id);
+
+-- (ingested_at, id) of the last click folded into the rollups; later clicks are read raw
+CREATE TABLE IF NOT EXISTS rollup_state (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    rolled_up_until TIMESTAMPTZ NOT NULL DEFAULT 'epoch',
+    last_click_id BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO rollup_state (id) VALUES (true) ON CONFLICT DO NOTHING;
@@ -0,0 +1,18 @@
+ALTER TABLE clicks RENAME TO clicks_partitioned;
+ALTER INDEX clicks_pkey RENAME TO clicks_partitioned_pkey;
+
+CREATE TABLE clicks (
+    LIKE clicks_partitioned INCLUDING DEFAULTS INCLUDING CONSTRAINTS,
+    PRIMARY KEY (id)
+);
+
+ALTER SEQUENCE clicks_id_seq OWNED BY clicks.id;
+
+INSERT INTO clicks SELECT * FROM clicks_partitioned;
+
+-- Drops the monthly partitions with it
+DROP TABLE clicks_partitioned;
+DROP FUNCTION IF EXISTS create_click_partitions(DATE, INT);
+
+CREATE INDEX IF NOT EXISTS idx_clicks_slug ON clicks (slug);
+CREATE INDEX IF NOT EXISTS idx_clicks_ingested_at ON clicks (ingested_at, id);
@@ -0,0 +1,72 @@
+-- Range-partitions clicks by UTC month. The primary key has to include the partition key.
+-- The whole migration runs in one transaction and holds an exclusive lock on clicks while the
+-- rows are copied, which takes a while on a large table. Redirects do not touch clicks and the
+-- consumer's inserts wait on the lock with the stream backing up, but analytics reads block too,
+-- so run it in a quiet period.
+ALTER TABLE clicks RENAME TO clicks_unpartitioned;
+ALTER INDEX clicks_pkey RENAME TO clicks_unpartitioned_pkey;
+DROP INDEX IF EXISTS idx_clicks_slug;
+DROP INDEX IF EXISTS idx_clicks_ingested_at;
+
+CREATE TABLE clicks (
+    LIKE clicks_unpartitioned INCLUDING DEFAULTS INCLUDING CONSTRAINTS,
+    PRIMARY KEY (id, timestamp)
+) PARTITION BY RANGE (timestamp);
+
+-- Keeps the id sequence when the old table is dropped
+ALTER SEQUENCE clicks_id_seq OWNED BY clicks.id;
+
+-- Analytics always filter by slug and a timestamp range
+CREATE INDEX IF NOT EXISTS idx_clicks_slug_timestamp ON clicks (slug, timestamp);
+-- Rollups read clicks in ingestion order
+CREATE INDEX IF NOT EXISTS idx_clicks_ingested_at ON clicks (ingested_at, id);
+
+-- Catches clicks outside every monthly partition, e.g. a skewed clock or a lapsed maintenance
+-- job, so their inserts do not fail
+CREATE TABLE IF NOT EXISTS clicks_default PARTITION OF clicks DEFAULT;
+
+-- Creates the missing monthly partitions from the month of `from_date` through
+-- `months_ahead` months after the current one, and returns how many were created.
+-- Clicks of a new partition's month that landed in clicks_default are moved into it.
+CREATE OR REPLACE FUNCTION create_click_partitions(from_date DATE, months_ahead INT) RETURNS INT AS $$
+DECLARE
+    month DATE := date_trunc('month', from_date);
+    last_month DATE := date_trunc('month', NOW() AT TIME ZONE 'UTC') + make_interval(months => months_ahead);
+    partition TEXT;
+    month_start TIMESTAMPTZ;
+    month_end TIMESTAMPTZ;
+    created INT := 0;
+BEGIN
+    WHILE month <= last_month LOOP
+        partition := 'clicks_' || to_char(month, 'YYYY_MM');
+        month_start := month::timestamp AT TIME ZONE 'UTC';
+        month_end := (month + interval '1 month')::timestamp AT TIME ZONE 'UTC';
+        IF to_regclass(partition) IS NULL THEN
+            -- Attaching checks that the default partition holds no rows of the month
+            EXECUTE format('CREATE TABLE %I (LIKE clicks INCLUDING DEFAULTS INCLUDING CONSTRAINTS)', partition);
+            EXECUTE format(
+                'WITH moved AS (DELETE FROM clicks_default WHERE timestamp >= %L AND timestamp < %L RETURNING *)
+                 INSERT INTO %I SELECT * FROM moved',
+                month_start, month_end, partition
+            );
+            EXECUTE format(
+                'ALTER TABLE clicks ATTACH PARTITION %I FOR VALUES FROM (%L) TO (%L)',
+                partition, month_start, month_end
+            );
+            created := created + 1;
+        END IF;
+        month := month + interval '1 month';
+    END LOOP;
+
+    RETURN created;
+END;
+$$ LANGUAGE plpgsql;
+
+SELECT create_click_partitions(
+    COALESCE((SELECT MIN(timestamp AT TIME ZONE 'UTC')::date FROM clicks_unpartitioned), CURRENT_DATE),
+    3
+);
+
+INSERT INTO clicks SELECT * FROM clicks_unpartitioned;
+
+DROP TABLE clicks_unpartitioned;
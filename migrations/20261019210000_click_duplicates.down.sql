@@ -0,0 +1,13 @@
+-- Rollups are rebuilt from the raw clicks, which folds duplicates back into the regular counts
+TRUNCATE click_rollups_hourly, click_rollups_daily;
+UPDATE rollup_state SET last_click_id = 0, updated_at = NOW();
+
+ALTER TABLE click_rollups_daily DROP CONSTRAINT click_rollups_daily_pkey;
+ALTER TABLE click_rollups_daily DROP COLUMN IF EXISTS is_duplicate;
+ALTER TABLE click_rollups_daily ADD PRIMARY KEY (slug, timestamp, dimension, value, country, is_bot);
+
+ALTER TABLE click_rollups_hourly DROP CONSTRAINT click_rollups_hourly_pkey;
+ALTER TABLE click_rollups_hourly DROP COLUMN IF EXISTS is_duplicate;
+ALTER TABLE click_rollups_hourly ADD PRIMARY KEY (slug, timestamp, dimension, value, country, is_bot);
+
+ALTER TABLE clicks DROP COLUMN IF EXISTS is_duplicate;
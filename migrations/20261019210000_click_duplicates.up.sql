@@ -0,0 +1,11 @@
+-- Repeat clicks by the same visitor within the dedupe window; stored and flagged rather than dropped
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS is_duplicate BOOLEAN NOT NULL DEFAULT false;
+
+-- Rollups keep duplicates apart so deduplicated counts can still be read from them
+ALTER TABLE click_rollups_hourly ADD COLUMN IF NOT EXISTS is_duplicate BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE click_rollups_hourly DROP CONSTRAINT click_rollups_hourly_pkey;
+ALTER TABLE click_rollups_hourly ADD PRIMARY KEY (slug, timestamp, dimension, value, country, is_bot, is_duplicate);
+
+ALTER TABLE click_rollups_daily ADD COLUMN IF NOT EXISTS is_duplicate BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE click_rollups_daily DROP CONSTRAINT click_rollups_daily_pkey;
+ALTER TABLE click_rollups_daily ADD PRIMARY KEY (slug, timestamp, dimension, value, country, is_bot, is_duplicate);
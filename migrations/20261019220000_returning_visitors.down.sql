@@ -0,0 +1,3 @@
+ALTER TABLE clicks DROP COLUMN IF EXISTS is_returning;
+
+DROP TABLE IF EXISTS link_visitors;
@@ -0,0 +1,13 @@
+-- Visitors seen per link: the first-party cookie id, or the daily visitor hash for cookieless clicks
+CREATE TABLE IF NOT EXISTS link_visitors (
+    slug VARCHAR(32) NOT NULL,
+    visitor_key TEXT NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (slug, visitor_key)
+);
+
+-- Expired visitors are pruned by age
+CREATE INDEX IF NOT EXISTS idx_link_visitors_first_seen_at ON link_visitors (first_seen_at);
+
+-- NULL for clicks recorded before visitors were tracked
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS is_returning BOOLEAN;
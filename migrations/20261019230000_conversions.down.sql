@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS conversions;
+
+ALTER TABLE link_revisions DROP COLUMN IF EXISTS track_conversions;
+ALTER TABLE links DROP COLUMN IF EXISTS track_conversions;
+
+DROP INDEX IF EXISTS idx_clicks_click_id;
+ALTER TABLE clicks DROP COLUMN IF EXISTS click_id;
@@ -0,0 +1,43 @@
+-- Id appended to the destination URL of tracked clicks, echoed back when the visitor converts
+ALTER TABLE clicks ADD COLUMN IF NOT EXISTS click_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_clicks_click_id ON clicks (click_id);
+
+-- Whether the click id is appended to the destination URL; opt-in, since destinations like
+-- presigned URLs reject query parameters they did not sign
+ALTER TABLE links ADD COLUMN IF NOT EXISTS track_conversions BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Revisions capture the setting too, so a rollback restores it with the rest of the link
+ALTER TABLE link_revisions ADD COLUMN IF NOT EXISTS track_conversions BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- A click converts at most once per goal, so repeated pixels and postbacks are ignored.
+-- Pixel conversions can arrive before their click leaves the stream. Until the click is stored
+-- they are pending, and they are dropped if it has not shown up by `pending_until`.
+CREATE TABLE IF NOT EXISTS conversions (
+    id BIGSERIAL PRIMARY KEY,
+    click_id TEXT NOT NULL,
+    goal TEXT NOT NULL DEFAULT 'default',
+    source TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    pending_until TIMESTAMPTZ,
+    CONSTRAINT conversions_click_goal_key UNIQUE (click_id, goal)
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversions_pending_until ON conversions (pending_until) WHERE pending_until IS NOT NULL;
+
+-- Slugs and aliases named after top-level API routes never reached the redirect. They are
+-- rejected from now on; existing ones are reported here so they can be renamed first.
+DO $$
+DECLARE
+    reserved TEXT[] := ARRAY['campaigns', 'conversions', 'convert', 'links', 'shorten'];
+    taken TEXT;
+BEGIN
+    SELECT string_agg(name, ', ') INTO taken FROM (
+        SELECT slug AS name FROM links WHERE slug = ANY(reserved)
+        UNION ALL
+        SELECT alias FROM link_aliases WHERE alias = ANY(reserved)
+    ) names;
+
+    IF taken IS NOT NULL THEN
+        RAISE EXCEPTION 'Slugs or aliases use names reserved for API routes: %. Rename them before migrating.', taken;
+    END IF;
+END $$;
@@ -1,9 +1,34 @@
 use dotenvy::dotenv;
-use std::env;
+use std::{collections::HashMap, env, time::Duration};
 
+// How the visitor IP is kept on stored clicks, from IP_STORAGE=raw|truncated|hashed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpStorage {
+    Raw,
+    // IPv4 cut to its /24 network, IPv6 to its /48
+    Truncated,
+    // No address at all; only the daily visitor hash remains
+    Hashed,
+}
+
+#[derive(Clone)]
 pub struct Config{
     pub port : u16,
     pub db_url: String,
+    pub public_base_url: String,
+    pub idempotency_ttl: Duration,
+    // API key -> owner name, parsed from API_KEYS="owner:key,owner2:key2"
+    pub api_keys: HashMap<String, String>,
+    // Extra referrer domain -> channel rules, from REFERRER_CHANNEL_RULES="domain:channel,..."
+    pub referrer_channel_rules: Vec<(String, String)>,
+    // Referrer domains treated as internal, from INTERNAL_REFERRER_DOMAINS="a.com,b.com"
+    pub internal_referrer_domains: Vec<String>,
+    // MaxMind-format databases used to geolocate clicks, reloaded when replaced
+    pub geoip_db_path: Option<String>,
+    pub geoip_asn_db_path: Option<String>,
+    pub ip_storage: IpStorage,
+    // Repeat clicks by one visitor on one link within this window are flagged as duplicates; zero disables
+    pub click_dedupe_window: Duration,
 }
 
 impl Config{
@@ -11,7 +36,67 @@ impl Config{
         dotenv().ok();
         let port = env::var("PORT").unwrap_or_else(|_| "8080".into()).parse().unwrap();
         let db_url = env::var("DATABASE_URL").unwrap();
+
+        // Base URL used to build the short links handed back to clients
+        let public_base_url = env::var("PUBLIC_BASE_URL")
+            .unwrap_or_else(|_| format!("http://localhost:{}", port))
+            .trim_end_matches('/')
+            .to_string();
+
+        // How long an Idempotency-Key is remembered for replays
+        let idempotency_ttl = env::var("IDEMPOTENCY_KEY_TTL")
+            .ok()
+            .and_then(|raw| humantime::parse_duration(&raw).ok())
+            .unwrap_or(Duration::from_secs(24 * 60 * 60));
+
+        let api_keys = env::var("API_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| entry.trim().split_once(':'))
+            .map(|(owner, key)| (key.trim().to_string(), owner.trim().to_string()))
+            .collect();
+
+        let referrer_channel_rules = env::var("REFERRER_CHANNEL_RULES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| entry.trim().split_once(':'))
+            .map(|(domain, channel)| (domain.trim().to_string(), channel.trim().to_lowercase()))
+            .collect();
+
+        let internal_referrer_domains = env::var("INTERNAL_REFERRER_DOMAINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|domain| domain.trim().to_string())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+
+        let geoip_db_path = env::var("GEOIP_DB_PATH").ok().filter(|path| !path.is_empty());
+        let geoip_asn_db_path = env::var("GEOIP_ASN_DB_PATH").ok().filter(|path| !path.is_empty());
+
+        let ip_storage = match env::var("IP_STORAGE").unwrap_or_default().trim().to_lowercase().as_str() {
+            "" | "raw" => IpStorage::Raw,
+            "truncated" => IpStorage::Truncated,
+            "hashed" => IpStorage::Hashed,
+            other => panic!("Invalid IP_STORAGE '{}': expected raw, truncated or hashed", other),
+        };
+
+        let click_dedupe_window = env::var("CLICK_DEDUPE_WINDOW")
+            .ok()
+            .and_then(|raw| humantime::parse_duration(&raw).ok())
+            .unwrap_or(Duration::from_secs(10));
         
-        Self { port, db_url }
+        Self {
+            port,
+            db_url,
+            public_base_url,
+            idempotency_ttl,
+            api_keys,
+            referrer_channel_rules,
+            internal_referrer_domains,
+            geoip_db_path,
+            geoip_asn_db_path,
+            ip_storage,
+            click_dedupe_window,
+        }
           }
-}
\ No newline at end of file
+}
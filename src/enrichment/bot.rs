@@ -0,0 +1,231 @@
+use crate::models::click::ClickEvent;
+
+pub const REASON_CRAWLER: &str = "crawler";
+pub const REASON_UA_PATTERN: &str = "ua_pattern";
+pub const REASON_HEADLESS: &str = "headless";
+pub const REASON_PREFETCH: &str = "prefetch";
+
+// Link unfurlers, search crawlers and mail security scanners, matched case-insensitively
+// against the user agent. The first entry found names the bot.
+const KNOWN_CRAWLERS: &[(&str, &str)] = &[
+    ("facebookexternalhit", "Facebook"),
+    ("facebot", "Facebook"),
+    ("twitterbot", "Twitterbot"),
+    ("slackbot", "Slackbot"),
+    ("slack-imgproxy", "Slackbot"),
+    ("linkedinbot", "LinkedInBot"),
+    ("discordbot", "Discordbot"),
+    ("telegrambot", "TelegramBot"),
+    ("whatsapp", "WhatsApp"),
+    ("skypeuripreview", "Skype"),
+    ("microsoftpreview", "Microsoft Preview"),
+    ("bingpreview", "BingPreview"),
+    ("googlebot", "Googlebot"),
+    ("adsbot-google", "Googlebot"),
+    ("google-inspectiontool", "Googlebot"),
+    ("googleother", "Googlebot"),
+    ("bingbot", "Bingbot"),
+    ("applebot", "Applebot"),
+    ("duckduckbot", "DuckDuckBot"),
+    ("yandexbot", "YandexBot"),
+    ("baiduspider", "Baiduspider"),
+    ("pinterestbot", "Pinterestbot"),
+    ("redditbot", "Redditbot"),
+    ("embedly", "Embedly"),
+    ("iframely", "Iframely"),
+    ("mastodon", "Mastodon"),
+    ("ahrefsbot", "AhrefsBot"),
+    ("semrushbot", "SemrushBot"),
+    ("mj12bot", "MJ12bot"),
+    ("petalbot", "PetalBot"),
+    ("gptbot", "GPTBot"),
+    ("claudebot", "ClaudeBot"),
+    ("ccbot", "CCBot"),
+    ("bytespider", "Bytespider"),
+    ("amazonbot", "Amazonbot"),
+    ("ia_archiver", "Internet Archive"),
+    ("barracuda", "Barracuda"),
+    ("proofpoint", "Proofpoint"),
+    ("mimecast", "Mimecast"),
+    ("urlscan", "urlscan.io"),
+];
+
+// Automation tools and HTTP client libraries, plus generic words crawlers identify with
+const UA_PATTERNS: &[(&str, &str)] = &[
+    ("curl/", "curl"),
+    ("wget/", "Wget"),
+    ("python-requests", "python-requests"),
+    ("python-urllib", "Python urllib"),
+    ("aiohttp", "aiohttp"),
+    ("python-httpx", "httpx"),
+    ("go-http-client", "Go http client"),
+    ("okhttp", "OkHttp"),
+    ("java/", "Java"),
+    ("apache-httpclient", "Apache HttpClient"),
+    ("libwww-perl", "libwww-perl"),
+    ("node-fetch", "node-fetch"),
+    ("axios/", "axios"),
+    ("postmanruntime", "Postman"),
+    ("httpie", "HTTPie"),
+    ("scrapy", "Scrapy"),
+    ("scanner", "Other bot"),
+    ("preview", "Other bot"),
+];
+
+// Only matched where they end a product token ("SomeBot/1.0", "compatible; SomeBot)"), since
+// device and model names such as "CUBOT P50" contain them too
+const GENERIC_BOT_WORDS: &[&str] = &["bot", "crawler", "spider"];
+
+const HEADLESS_MARKERS: &[(&str, &str)] = &[
+    ("headlesschrome", "HeadlessChrome"),
+    ("phantomjs", "PhantomJS"),
+    ("puppeteer", "Puppeteer"),
+    ("playwright", "Playwright"),
+    ("selenium", "Selenium"),
+    ("slimerjs", "SlimerJS"),
+];
+
+#[derive(Debug, PartialEq)]
+pub struct BotMatch {
+    pub reason: &'static str,
+    pub name: String,
+}
+
+pub fn classify_bot(event: &ClickEvent) -> Option<BotMatch> {
+    // Speculative loads were never followed by a person, whatever the client
+    if is_prefetch(event.purpose.as_deref()) {
+        return Some(BotMatch { reason: REASON_PREFETCH, name: "Prefetch".to_string() });
+    }
+
+    let user_agent = event.user_agent.to_lowercase();
+    if user_agent.is_empty() || user_agent == "unknown" {
+        return Some(BotMatch { reason: REASON_UA_PATTERN, name: "Missing user agent".to_string() });
+    }
+
+    if let Some(name) = find(KNOWN_CRAWLERS, &user_agent) {
+        return Some(BotMatch { reason: REASON_CRAWLER, name });
+    }
+    if let Some(name) = find(HEADLESS_MARKERS, &user_agent) {
+        return Some(BotMatch { reason: REASON_HEADLESS, name });
+    }
+    if let Some(name) = find(UA_PATTERNS, &user_agent) {
+        return Some(BotMatch { reason: REASON_UA_PATTERN, name });
+    }
+    if GENERIC_BOT_WORDS.iter().any(|word| ends_token(&user_agent, word)) {
+        return Some(BotMatch { reason: REASON_UA_PATTERN, name: "Other bot".to_string() });
+    }
+
+    // Real browsers always send Accept-Language; automation claiming to be one often does not
+    if user_agent.starts_with("mozilla/") && event.accept_language.as_deref().is_none_or(str::is_empty) {
+        return Some(BotMatch { reason: REASON_HEADLESS, name: "Browser without Accept-Language".to_string() });
+    }
+
+    None
+}
+
+fn find(table: &[(&str, &str)], user_agent: &str) -> Option<String> {
+    table
+        .iter()
+        .find(|(pattern, _)| user_agent.contains(pattern))
+        .map(|(_, name)| name.to_string())
+}
+
+// Whether `word` occurs at the end of a token: before a version, a separator or the end of the string
+fn ends_token(user_agent: &str, word: &str) -> bool {
+    user_agent.match_indices(word).any(|(index, _)| {
+        matches!(user_agent[index + word.len()..].chars().next(), None | Some('/' | ';' | ')' | ','))
+    })
+}
+
+// `purpose` holds the Sec-Purpose, Purpose, X-Purpose or X-Moz header value
+fn is_prefetch(purpose: Option<&str>) -> bool {
+    purpose.is_some_and(|value| {
+        let value = value.to_lowercase();
+        value.contains("prefetch") || value.contains("prerender") || value.contains("preview")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+    fn click(user_agent: &str, accept_language: Option<&str>, purpose: Option<&str>) -> ClickEvent {
+        ClickEvent {
+            slug: "abc123".to_string(),
+            ip: "203.0.113.7".to_string(),
+            user_agent: user_agent.to_string(),
+            referer: None,
+            timestamp: chrono::Utc::now(),
+            alias: None,
+            purpose: purpose.map(str::to_string),
+            accept_language: accept_language.map(str::to_string),
+            visitor_cookie: None,
+            visitor_cookie_issued: false,
+            click_id: None,
+        }
+    }
+
+    fn reason(event: &ClickEvent) -> Option<&'static str> {
+        classify_bot(event).map(|bot| bot.reason)
+    }
+
+    #[test]
+    fn real_browsers_are_not_bots() {
+        assert_eq!(classify_bot(&click(CHROME, Some("en-US,en;q=0.9"), None)), None);
+    }
+
+    #[test]
+    fn prefetches_are_bots_whatever_the_client() {
+        assert_eq!(reason(&click(CHROME, Some("en"), Some("prefetch;prerender"))), Some(REASON_PREFETCH));
+        assert_eq!(reason(&click(CHROME, Some("en"), Some("Preview"))), Some(REASON_PREFETCH));
+    }
+
+    #[test]
+    fn known_crawlers_are_named() {
+        let bot = classify_bot(&click("Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)", None, None)).unwrap();
+        assert_eq!(bot, BotMatch { reason: REASON_CRAWLER, name: "Slackbot".to_string() });
+
+        let bot = classify_bot(&click("facebookexternalhit/1.1", None, None)).unwrap();
+        assert_eq!(bot.name, "Facebook");
+    }
+
+    #[test]
+    fn headless_browsers_are_detected() {
+        let ua = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) HeadlessChrome/124.0.0.0 Safari/537.36";
+        assert_eq!(reason(&click(ua, Some("en"), None)), Some(REASON_HEADLESS));
+    }
+
+    #[test]
+    fn http_clients_and_missing_agents_match_patterns() {
+        assert_eq!(classify_bot(&click("curl/8.5.0", None, None)).unwrap().name, "curl");
+        assert_eq!(reason(&click("python-requests/2.31", None, None)), Some(REASON_UA_PATTERN));
+        assert_eq!(reason(&click("Unknown", None, None)), Some(REASON_UA_PATTERN));
+        assert_eq!(reason(&click("", None, None)), Some(REASON_UA_PATTERN));
+        assert_eq!(reason(&click("SomeNewCrawler/1.0", None, None)), Some(REASON_UA_PATTERN));
+    }
+
+    #[test]
+    fn generic_bot_words_only_match_at_the_end_of_a_token() {
+        assert_eq!(reason(&click("Mozilla/5.0 (compatible; SomeBot; +https://example.com)", None, None)), Some(REASON_UA_PATTERN));
+        assert_eq!(reason(&click("ExampleSpider/2.1", None, None)), Some(REASON_UA_PATTERN));
+        assert_eq!(reason(&click("feedbot", None, None)), Some(REASON_UA_PATTERN));
+        assert!(!ends_token("robotics-app/1.0", "bot"));
+    }
+
+    #[test]
+    fn devices_named_like_bots_are_not_flagged() {
+        let cubot = "Mozilla/5.0 (Linux; Android 11; CUBOT P50 Build/RKQ1.210503.001) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36";
+        assert_eq!(classify_bot(&click(cubot, Some("en-GB,en;q=0.9"), None)), None);
+
+        let cubot_model = "Mozilla/5.0 (Linux; Android 10; CUBOT_X30) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+        assert_eq!(classify_bot(&click(cubot_model, Some("en"), None)), None);
+    }
+
+    #[test]
+    fn browsers_without_accept_language_are_treated_as_automation() {
+        assert_eq!(reason(&click(CHROME, None, None)), Some(REASON_HEADLESS));
+        assert_eq!(reason(&click(CHROME, Some(""), None)), Some(REASON_HEADLESS));
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    net::IpAddr,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use maxminddb::{geoip2, Reader};
+
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct GeoInfo {
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<i64>,
+    pub asn_org: Option<String>,
+}
+
+struct LoadedDatabase {
+    reader: Reader<Vec<u8>>,
+    modified: Option<SystemTime>,
+}
+
+// An .mmdb file that is swapped in place whenever it changes on disk
+struct WatchedDatabase {
+    path: PathBuf,
+    loaded: RwLock<Option<LoadedDatabase>>,
+}
+
+impl WatchedDatabase {
+    fn open(path: PathBuf) -> Self {
+        let database = Self { path, loaded: RwLock::new(None) };
+        database.reload_if_changed();
+        database
+    }
+
+    fn reload_if_changed(&self) {
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+
+        let current = self.loaded.read().unwrap().as_ref().map(|db| db.modified);
+        if current.is_some_and(|loaded| loaded == modified) {
+            return;
+        }
+
+        match Reader::open_readfile(&self.path) {
+            Ok(reader) => {
+                tracing::info!("Loaded GeoIP database {}", self.path.display());
+                *self.loaded.write().unwrap() = Some(LoadedDatabase { reader, modified });
+            }
+            Err(e) => {
+                // Keep serving from the previous copy if the new one is unreadable
+                tracing::error!("Failed to load GeoIP database {}: {:?}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+pub struct GeoIpResolver {
+    city: Option<WatchedDatabase>,
+    asn: Option<WatchedDatabase>,
+}
+
+impl GeoIpResolver {
+    pub fn new(city_path: Option<&str>, asn_path: Option<&str>) -> Self {
+        Self {
+            city: city_path.map(|path| WatchedDatabase::open(PathBuf::from(path))),
+            asn: asn_path.map(|path| WatchedDatabase::open(PathBuf::from(path))),
+        }
+    }
+
+    pub fn lookup(&self, ip: &str) -> GeoInfo {
+        let mut info = GeoInfo::default();
+
+        let Ok(ip) = ip.parse::<IpAddr>() else {
+            return info;
+        };
+
+        if let Some(city_db) = &self.city {
+            if let Some(loaded) = city_db.loaded.read().unwrap().as_ref() {
+                if let Ok(record) = loaded.reader.lookup::<geoip2::City>(ip) {
+                    info.country = record.country.and_then(|c| c.iso_code).map(str::to_string);
+                    info.region = record.subdivisions
+                        .and_then(|subdivisions| subdivisions.into_iter().next())
+                        .and_then(|subdivision| english_name(subdivision.names));
+                    info.city = record.city.and_then(|city| english_name(city.names));
+                }
+
+                // Some databases carry ASN data alongside the city data
+                if let Ok(record) = loaded.reader.lookup::<geoip2::Asn>(ip) {
+                    info.asn = record.autonomous_system_number.map(i64::from);
+                    info.asn_org = record.autonomous_system_organization.map(str::to_string);
+                }
+            }
+        }
+
+        if let Some(asn_db) = &self.asn {
+            if let Some(loaded) = asn_db.loaded.read().unwrap().as_ref() {
+                if let Ok(record) = loaded.reader.lookup::<geoip2::Asn>(ip) {
+                    info.asn = record.autonomous_system_number.map(i64::from);
+                    info.asn_org = record.autonomous_system_organization.map(str::to_string);
+                }
+            }
+        }
+
+        info
+    }
+
+    fn reload_if_changed(&self) {
+        for database in [&self.city, &self.asn].into_iter().flatten() {
+            database.reload_if_changed();
+        }
+    }
+}
+
+fn english_name(names: Option<std::collections::BTreeMap<&str, &str>>) -> Option<String> {
+    names.and_then(|names| names.get("en").map(|name| name.to_string()))
+}
+
+// Periodically picks up replaced .mmdb files without restarting
+pub async fn watch_for_changes(resolver: Arc<GeoIpResolver>) {
+    let mut interval = tokio::time::interval(RELOAD_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let resolver = resolver.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || resolver.reload_if_changed()).await {
+            tracing::error!("GeoIP reload task failed: {:?}", e);
+        }
+    }
+}
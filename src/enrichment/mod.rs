@@ -0,0 +1,90 @@
+pub mod user_agent;
+pub mod referrer;
+pub mod geoip;
+pub mod bot;
+pub mod visitor;
+pub mod returning;
+
+use std::sync::Arc;
+
+use sqlx::PgPool;
+
+use crate::{
+    config::Config,
+    enrichment::{geoip::GeoIpResolver, referrer::ReferrerClassifier, returning::ReturningVisitors, visitor::VisitorHasher},
+    models::click::{ClickEnrichment, ClickEvent},
+};
+
+// Derives the stored click attributes from a raw event
+pub struct ClickEnricher {
+    referrers: ReferrerClassifier,
+    geoip: Arc<GeoIpResolver>,
+    visitors: VisitorHasher,
+    returning: ReturningVisitors,
+}
+
+impl ClickEnricher {
+    pub fn new(config: &Config, db: PgPool) -> Self {
+        // Clicks coming from our own short links count as internal traffic
+        let mut internal_domains = config.internal_referrer_domains.clone();
+        if let Some(host) = url::Url::parse(&config.public_base_url).ok().and_then(|u| u.host_str().map(str::to_string)) {
+            internal_domains.push(host);
+        }
+
+        Self {
+            referrers: ReferrerClassifier::new(&config.referrer_channel_rules, internal_domains),
+            geoip: Arc::new(GeoIpResolver::new(
+                config.geoip_db_path.as_deref(),
+                config.geoip_asn_db_path.as_deref(),
+            )),
+            visitors: VisitorHasher::new(db.clone(), config.ip_storage),
+            returning: ReturningVisitors::new(db),
+        }
+    }
+
+    pub fn geoip(&self) -> Arc<GeoIpResolver> {
+        self.geoip.clone()
+    }
+
+    pub async fn enrich(&self, event: &ClickEvent) -> ClickEnrichment {
+        let user_agent = user_agent::parse_user_agent(&event.user_agent);
+        let referrer = self.referrers.classify(event.referer.as_deref());
+        let geo = self.geoip.lookup(&event.ip);
+        let bot = bot::classify_bot(event);
+        let visitor_id = match self.visitors.visitor_id(event).await {
+            Ok(visitor_id) => Some(visitor_id),
+            Err(e) => {
+                tracing::warn!("Failed to hash visitor for slug {}: {:?}", event.slug, e);
+                None
+            }
+        };
+        let is_returning = match self.returning.is_returning(event, visitor_id.as_deref()).await {
+            Ok(is_returning) => is_returning,
+            Err(e) => {
+                tracing::warn!("Failed to look up returning visitor for slug {}: {:?}", event.slug, e);
+                None
+            }
+        };
+
+        ClickEnrichment {
+            ip: self.visitors.stored_ip(&event.ip),
+            visitor_id,
+            browser_family: user_agent.browser_family,
+            browser_version: user_agent.browser_version,
+            os_family: user_agent.os_family,
+            device_type: user_agent.device_type.to_string(),
+            referrer_domain: referrer.domain,
+            channel: referrer.channel.to_string(),
+            country: geo.country,
+            region: geo.region,
+            city: geo.city,
+            asn: geo.asn,
+            asn_org: geo.asn_org,
+            is_bot: bot.is_some(),
+            bot_reason: bot.as_ref().map(|b| b.reason.to_string()),
+            bot_name: bot.map(|b| b.name),
+            is_duplicate: false,
+            is_returning,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+pub const CHANNEL_DIRECT: &str = "direct";
+pub const CHANNEL_SEARCH: &str = "search";
+pub const CHANNEL_SOCIAL: &str = "social";
+pub const CHANNEL_EMAIL: &str = "email";
+pub const CHANNEL_INTERNAL: &str = "internal";
+pub const CHANNEL_OTHER: &str = "other";
+
+pub const CHANNELS: [&str; 6] = [
+    CHANNEL_DIRECT,
+    CHANNEL_SEARCH,
+    CHANNEL_SOCIAL,
+    CHANNEL_EMAIL,
+    CHANNEL_INTERNAL,
+    CHANNEL_OTHER,
+];
+
+// Domain patterns match the domain itself and its subdomains; a trailing ".*" matches any TLD.
+// The first matching rule wins, so more specific hosts are listed first.
+const BUILT_IN_RULES: &[(&str, &str)] = &[
+    ("mail.google.com", CHANNEL_EMAIL),
+    ("outlook.live.com", CHANNEL_EMAIL),
+    ("outlook.office.com", CHANNEL_EMAIL),
+    ("mail.yahoo.com", CHANNEL_EMAIL),
+    ("mail.proton.me", CHANNEL_EMAIL),
+    ("google.*", CHANNEL_SEARCH),
+    ("bing.com", CHANNEL_SEARCH),
+    ("duckduckgo.com", CHANNEL_SEARCH),
+    ("search.yahoo.com", CHANNEL_SEARCH),
+    ("baidu.com", CHANNEL_SEARCH),
+    ("yandex.*", CHANNEL_SEARCH),
+    ("ecosia.org", CHANNEL_SEARCH),
+    ("search.brave.com", CHANNEL_SEARCH),
+    ("facebook.com", CHANNEL_SOCIAL),
+    ("fb.me", CHANNEL_SOCIAL),
+    ("instagram.com", CHANNEL_SOCIAL),
+    ("t.co", CHANNEL_SOCIAL),
+    ("twitter.com", CHANNEL_SOCIAL),
+    ("x.com", CHANNEL_SOCIAL),
+    ("linkedin.com", CHANNEL_SOCIAL),
+    ("lnkd.in", CHANNEL_SOCIAL),
+    ("reddit.com", CHANNEL_SOCIAL),
+    ("youtube.com", CHANNEL_SOCIAL),
+    ("pinterest.com", CHANNEL_SOCIAL),
+    ("tiktok.com", CHANNEL_SOCIAL),
+    ("threads.net", CHANNEL_SOCIAL),
+    ("news.ycombinator.com", CHANNEL_SOCIAL),
+];
+
+#[derive(Debug, PartialEq)]
+pub struct ReferrerInfo {
+    pub domain: Option<String>,
+    pub channel: &'static str,
+}
+
+pub struct ReferrerClassifier {
+    // Configured rules come first so they can override the built-in ones
+    rules: Vec<(String, &'static str)>,
+    internal_domains: Vec<String>,
+}
+
+impl ReferrerClassifier {
+    pub fn new(custom_rules: &[(String, String)], internal_domains: Vec<String>) -> Self {
+        let mut rules: Vec<(String, &'static str)> = custom_rules
+            .iter()
+            .filter_map(|(pattern, channel)| {
+                match CHANNELS.iter().find(|known| **known == channel.as_str()) {
+                    Some(known) => Some((pattern.to_lowercase(), *known)),
+                    None => {
+                        tracing::warn!("Ignoring referrer rule for '{}': unknown channel '{}'", pattern, channel);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        rules.extend(BUILT_IN_RULES.iter().map(|(pattern, channel)| (pattern.to_string(), *channel)));
+
+        let internal_domains = internal_domains
+            .into_iter()
+            .map(|domain| strip_www(&domain.to_lowercase()).to_string())
+            .collect();
+
+        Self { rules, internal_domains }
+    }
+
+    pub fn classify(&self, referer: Option<&str>) -> ReferrerInfo {
+        let Some(referer) = referer.map(str::trim).filter(|r| !r.is_empty()) else {
+            return ReferrerInfo { domain: None, channel: CHANNEL_DIRECT };
+        };
+
+        let Some(domain) = referrer_domain(referer) else {
+            return ReferrerInfo { domain: None, channel: CHANNEL_OTHER };
+        };
+
+        let channel = if self.internal_domains.iter().any(|internal| matches_domain(&domain, internal)) {
+            CHANNEL_INTERNAL
+        } else {
+            self.rules
+                .iter()
+                .find(|(pattern, _)| matches_domain(&domain, pattern))
+                .map(|(_, channel)| *channel)
+                .unwrap_or(CHANNEL_OTHER)
+        };
+
+        ReferrerInfo { domain: Some(domain), channel }
+    }
+}
+
+// Reduces a referer URL to its lowercase host without a leading "www."
+pub fn referrer_domain(referer: &str) -> Option<String> {
+    let parsed = url::Url::parse(referer).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    Some(strip_www(&host).to_string())
+}
+
+fn strip_www(host: &str) -> &str {
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+// Second-level labels used under country TLDs, as in co.uk or com.br
+const COUNTRY_SECOND_LEVELS: &[&str] = &["co", "com", "net", "org", "gov", "edu", "ac", "ne", "or", "go"];
+
+fn matches_domain(domain: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix(".*") {
+        // "google.*" matches google.com, google.co.uk, news.google.de, but not google.evil.com
+        Some(base) => {
+            let labels: Vec<&str> = domain.split('.').collect();
+            (1..=2.min(labels.len())).any(|suffix_len| {
+                let (rest, suffix) = labels.split_at(labels.len() - suffix_len);
+                let rest = rest.join(".");
+                is_public_suffix(suffix) && (rest == base || rest.ends_with(&format!(".{}", base)))
+            })
+        }
+        None => domain == pattern || domain.ends_with(&format!(".{}", pattern)),
+    }
+}
+
+// A TLD, or a country TLD under one of the common second-level labels
+fn is_public_suffix(labels: &[&str]) -> bool {
+    match labels {
+        [tld] => !tld.is_empty() && tld.chars().all(|c| c.is_ascii_alphabetic()),
+        [second, country] => {
+            COUNTRY_SECOND_LEVELS.contains(second) && country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic())
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classifier() -> ReferrerClassifier {
+        ReferrerClassifier::new(
+            &[("news.example.com".to_string(), CHANNEL_EMAIL.to_string())],
+            vec!["www.linkping.io".to_string()],
+        )
+    }
+
+    #[test]
+    fn wildcard_patterns_match_any_tld_and_subdomains() {
+        assert!(matches_domain("google.com", "google.*"));
+        assert!(matches_domain("google.co.uk", "google.*"));
+        assert!(matches_domain("news.google.de", "google.*"));
+        assert!(matches_domain("yandex.com.tr", "yandex.*"));
+    }
+
+    #[test]
+    fn wildcard_patterns_do_not_match_other_registrable_domains() {
+        assert!(!matches_domain("google.evil.com", "google.*"));
+        assert!(!matches_domain("x.google.evil.com", "google.*"));
+        assert!(!matches_domain("notgoogle.com", "google.*"));
+        assert!(!matches_domain("google", "google.*"));
+    }
+
+    #[test]
+    fn plain_patterns_match_the_domain_and_its_subdomains() {
+        assert!(matches_domain("t.co", "t.co"));
+        assert!(matches_domain("m.facebook.com", "facebook.com"));
+        assert!(!matches_domain("notfacebook.com", "facebook.com"));
+        assert!(!matches_domain("facebook.com.evil.net", "facebook.com"));
+    }
+
+    #[test]
+    fn referrer_domain_lowercases_and_strips_www() {
+        assert_eq!(referrer_domain("https://WWW.Example.com/path?q=1").as_deref(), Some("example.com"));
+        assert_eq!(referrer_domain("not a url"), None);
+    }
+
+    #[test]
+    fn classifies_channels() {
+        let classifier = classifier();
+        let channel = |referer| classifier.classify(referer).channel;
+
+        assert_eq!(channel(None), CHANNEL_DIRECT);
+        assert_eq!(channel(Some("  ")), CHANNEL_DIRECT);
+        assert_eq!(channel(Some("garbage")), CHANNEL_OTHER);
+        assert_eq!(channel(Some("https://www.google.co.uk/search")), CHANNEL_SEARCH);
+        assert_eq!(channel(Some("https://mail.google.com/mail/u/0")), CHANNEL_EMAIL);
+        assert_eq!(channel(Some("https://l.facebook.com/")), CHANNEL_SOCIAL);
+        assert_eq!(channel(Some("https://google.evil.com/")), CHANNEL_OTHER);
+        assert_eq!(channel(Some("https://blog.linkping.io/post")), CHANNEL_INTERNAL);
+    }
+
+    #[test]
+    fn configured_rules_override_built_in_ones() {
+        let info = classifier().classify(Some("https://news.example.com/issue/12"));
+        assert_eq!(info, ReferrerInfo { domain: Some("news.example.com".to_string()), channel: CHANNEL_EMAIL });
+    }
+
+    #[test]
+    fn rules_with_unknown_channels_are_ignored() {
+        let classifier = ReferrerClassifier::new(&[("example.org".to_string(), "carrier-pigeon".to_string())], Vec::new());
+        assert_eq!(classifier.classify(Some("https://example.org/")).channel, CHANNEL_OTHER);
+    }
+}
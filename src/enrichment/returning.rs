@@ -0,0 +1,88 @@
+use std::sync::Mutex;
+
+use chrono::{Duration, NaiveDate, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    enrichment::visitor::SALT_GRACE_DAYS,
+    models::click::{ClickEvent, VISITOR_COOKIE_MAX_AGE_SECS},
+};
+
+// Tells first-time visitors of a link from returning ones
+pub struct ReturningVisitors {
+    db: PgPool,
+    pruned_on: Mutex<Option<NaiveDate>>,
+}
+
+impl ReturningVisitors {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            db,
+            pruned_on: Mutex::new(None),
+        }
+    }
+
+    // Records the visitor against the link and reports whether it had been seen before.
+    // Visitors are known by their cookie; without one (cookies blocked, or first click) they
+    // are matched on the daily visitor hash, so a cookieless visitor is only recognised on
+    // the same day. `None` when the click carries neither.
+    pub async fn is_returning(
+        &self,
+        event: &ClickEvent,
+        visitor_id: Option<&str>,
+    ) -> Result<Option<bool>, sqlx::Error> {
+        self.prune().await?;
+
+        let visitor_key = match (&event.visitor_cookie, event.visitor_cookie_issued, visitor_id) {
+            (Some(cookie), false, _) => format!("c:{}", cookie),
+            (_, _, Some(visitor_id)) => format!("h:{}", visitor_id),
+            _ => return Ok(None),
+        };
+        let is_returning = !self.remember(&event.slug, &visitor_key).await?;
+
+        // The cookie issued with this click identifies the visitor from the next click on
+        if let (Some(cookie), true) = (&event.visitor_cookie, event.visitor_cookie_issued) {
+            self.remember(&event.slug, &format!("c:{}", cookie)).await?;
+        }
+
+        Ok(Some(is_returning))
+    }
+
+    // Whether the visitor was newly added
+    async fn remember(&self, slug: &str, visitor_key: &str) -> Result<bool, sqlx::Error> {
+        let inserted = sqlx::query!(
+            "INSERT INTO link_visitors (slug, visitor_key) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            slug,
+            visitor_key
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(inserted.rows_affected() == 1)
+    }
+
+    // Once a day, forgets visitors that can no longer come back under the same key: hashed
+    // ones once their salt is gone, cookie ones once the cookie has expired
+    async fn prune(&self) -> Result<(), sqlx::Error> {
+        let today = Utc::now().date_naive();
+        if *self.pruned_on.lock().unwrap() == Some(today) {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let hashed_cutoff = now - Duration::days(SALT_GRACE_DAYS + 1);
+        let cookie_cutoff = now - Duration::seconds(VISITOR_COOKIE_MAX_AGE_SECS as i64);
+        sqlx::query!(
+            "DELETE FROM link_visitors
+             WHERE (visitor_key LIKE 'h:%' AND first_seen_at < $1)
+                OR first_seen_at < $2",
+            hashed_cutoff,
+            cookie_cutoff
+        )
+        .execute(&self.db)
+        .await?;
+
+        *self.pruned_on.lock().unwrap() = Some(today);
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use woothee::parser::Parser;
+
+const UNKNOWN: &str = "Unknown";
+
+pub const DEVICE_DESKTOP: &str = "desktop";
+pub const DEVICE_MOBILE: &str = "mobile";
+pub const DEVICE_TABLET: &str = "tablet";
+pub const DEVICE_BOT: &str = "bot";
+pub const DEVICE_OTHER: &str = "other";
+
+#[derive(Debug)]
+pub struct ParsedUserAgent {
+    pub browser_family: String,
+    pub browser_version: Option<String>,
+    pub os_family: String,
+    pub device_type: &'static str,
+}
+
+pub fn parse_user_agent(user_agent: &str) -> ParsedUserAgent {
+    let Some(result) = Parser::new().parse(user_agent) else {
+        return ParsedUserAgent {
+            browser_family: UNKNOWN.to_string(),
+            browser_version: None,
+            os_family: UNKNOWN.to_string(),
+            device_type: DEVICE_OTHER,
+        };
+    };
+
+    // Only the major version is kept so breakdowns stay readable
+    let browser_version = result.version
+        .split('.')
+        .next()
+        .filter(|major| !major.is_empty() && major.chars().all(|c| c.is_ascii_digit()))
+        .map(|major| major.to_string());
+
+    ParsedUserAgent {
+        browser_family: known_or_unknown(result.name),
+        browser_version,
+        os_family: os_family(result.os),
+        device_type: device_type(result.category, result.os, user_agent),
+    }
+}
+
+fn known_or_unknown(value: &str) -> String {
+    if value == woothee::woothee::VALUE_UNKNOWN {
+        UNKNOWN.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+// woothee reports e.g. "Windows 10" or "iPhone"; collapse those into families
+fn os_family(os: &str) -> String {
+    match os {
+        os if os.starts_with("Windows") => "Windows".to_string(),
+        "iPhone" | "iPad" | "iPod" => "iOS".to_string(),
+        "Mac OSX" => "macOS".to_string(),
+        os => known_or_unknown(os),
+    }
+}
+
+fn device_type(category: &str, os: &str, user_agent: &str) -> &'static str {
+    // woothee has no tablet category, so detect the common tablet signatures
+    let is_tablet = os == "iPad"
+        || user_agent.contains("Tablet")
+        || (os == "Android" && !user_agent.contains("Mobile"));
+
+    match category {
+        "crawler" => DEVICE_BOT,
+        "smartphone" | "mobilephone" if is_tablet => DEVICE_TABLET,
+        "smartphone" | "mobilephone" => DEVICE_MOBILE,
+        "pc" => DEVICE_DESKTOP,
+        _ => DEVICE_OTHER,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_desktop_chrome_on_windows() {
+        let parsed = parse_user_agent(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.6367.91 Safari/537.36",
+        );
+        assert_eq!(parsed.browser_family, "Chrome");
+        assert_eq!(parsed.browser_version.as_deref(), Some("124"));
+        assert_eq!(parsed.os_family, "Windows");
+        assert_eq!(parsed.device_type, DEVICE_DESKTOP);
+    }
+
+    #[test]
+    fn parses_iphone_safari_as_mobile_ios() {
+        let parsed = parse_user_agent(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1",
+        );
+        assert_eq!(parsed.browser_family, "Safari");
+        assert_eq!(parsed.os_family, "iOS");
+        assert_eq!(parsed.device_type, DEVICE_MOBILE);
+    }
+
+    #[test]
+    fn detects_tablets() {
+        let ipad = parse_user_agent(
+            "Mozilla/5.0 (iPad; CPU OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1",
+        );
+        assert_eq!(ipad.device_type, DEVICE_TABLET);
+
+        let android_tablet = parse_user_agent(
+            "Mozilla/5.0 (Linux; Android 13; SM-X200) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+        );
+        assert_eq!(android_tablet.device_type, DEVICE_TABLET);
+    }
+
+    #[test]
+    fn reports_crawlers_as_bots() {
+        let parsed = parse_user_agent("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)");
+        assert_eq!(parsed.device_type, DEVICE_BOT);
+    }
+
+    #[test]
+    fn unknown_agents_fall_back_to_unknown() {
+        let parsed = parse_user_agent("");
+        assert_eq!(parsed.browser_family, UNKNOWN);
+        assert_eq!(parsed.browser_version, None);
+        assert_eq!(parsed.os_family, UNKNOWN);
+        assert_eq!(parsed.device_type, DEVICE_OTHER);
+    }
+}
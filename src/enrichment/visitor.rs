@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Mutex,
+};
+
+use chrono::{Duration, NaiveDate, Utc};
+use rand::{rng, Rng};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::{config::IpStorage, models::click::ClickEvent};
+
+// Salts stay around for one extra day so events still queued at midnight hash with their own day's salt
+pub const SALT_GRACE_DAYS: i64 = 1;
+
+// Derives a visitor hash from IP + user agent with a salt that rotates daily,
+// so the same visitor can be counted once per day but never tracked across days
+pub struct VisitorHasher {
+    db: PgPool,
+    ip_storage: IpStorage,
+    salts: Mutex<HashMap<NaiveDate, String>>,
+}
+
+impl VisitorHasher {
+    pub fn new(db: PgPool, ip_storage: IpStorage) -> Self {
+        Self {
+            db,
+            ip_storage,
+            salts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn visitor_id(&self, event: &ClickEvent) -> Result<String, sqlx::Error> {
+        let oldest_day = Utc::now().date_naive() - Duration::days(SALT_GRACE_DAYS);
+        // Events older than the retained salts hash with the oldest one still available
+        let day = event.timestamp.date_naive().max(oldest_day);
+        let salt = self.salt_for(day, oldest_day).await?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(salt.as_bytes());
+        hasher.update(b"|");
+        hasher.update(event.ip.as_bytes());
+        hasher.update(b"|");
+        hasher.update(event.user_agent.as_bytes());
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    // The IP as it should be written to the clicks table
+    pub fn stored_ip(&self, ip: &str) -> Option<String> {
+        match self.ip_storage {
+            IpStorage::Raw => Some(ip.to_string()),
+            IpStorage::Truncated => Some(truncate_ip(ip)),
+            IpStorage::Hashed => None,
+        }
+    }
+
+    async fn salt_for(&self, day: NaiveDate, oldest_day: NaiveDate) -> Result<String, sqlx::Error> {
+        if let Some(salt) = self.salts.lock().unwrap().get(&day) {
+            return Ok(salt.clone());
+        }
+
+        // Several consumers may race to create the salt; the first insert wins
+        let candidate = hex::encode(rng().random::<[u8; 32]>());
+        sqlx::query!(
+            "INSERT INTO visitor_salts (day, salt) VALUES ($1, $2) ON CONFLICT (day) DO NOTHING",
+            day,
+            candidate
+        )
+        .execute(&self.db)
+        .await?;
+
+        let salt = sqlx::query_scalar!("SELECT salt FROM visitor_salts WHERE day = $1", day)
+            .fetch_one(&self.db)
+            .await?;
+
+        // Rotation: forget salts past the grace period so old hashes can no longer be reproduced
+        sqlx::query!("DELETE FROM visitor_salts WHERE day < $1", oldest_day)
+            .execute(&self.db)
+            .await?;
+
+        let mut salts = self.salts.lock().unwrap();
+        salts.retain(|cached_day, _| *cached_day >= oldest_day);
+        salts.insert(day, salt.clone());
+
+        Ok(salt)
+    }
+}
+
+// Zeroes the host part: IPv4 to /24, IPv6 to /48. Unparseable values are kept as they are.
+fn truncate_ip(ip: &str) -> String {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(v4)) => {
+            let [a, b, c, _] = v4.octets();
+            Ipv4Addr::new(a, b, c, 0).to_string()
+        }
+        Ok(IpAddr::V6(v6)) => {
+            let segments = v6.segments();
+            Ipv6Addr::new(segments[0], segments[1], segments[2], 0, 0, 0, 0, 0).to_string()
+        }
+        Err(_) => ip.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_ipv4_to_a_24() {
+        assert_eq!(truncate_ip("203.0.113.77"), "203.0.113.0");
+        assert_eq!(truncate_ip("10.1.2.0"), "10.1.2.0");
+    }
+
+    #[test]
+    fn truncates_ipv6_to_a_48() {
+        assert_eq!(truncate_ip("2001:db8:85a3:8d3:1319:8a2e:370:7348"), "2001:db8:85a3::");
+        assert_eq!(truncate_ip("::1"), "::");
+    }
+
+    #[test]
+    fn keeps_unparseable_values() {
+        assert_eq!(truncate_ip("Unknown"), "Unknown");
+        assert_eq!(truncate_ip(""), "");
+    }
+}
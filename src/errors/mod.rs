@@ -10,6 +10,7 @@ pub enum AppError{
     NotFound(String),
     InternalServerError(String),
     Unauthorized(String),
+    UnprocessableEntity(String),
 }
 
 #[derive(Serialize)]
@@ -25,6 +26,7 @@ impl IntoResponse for AppError{
             AppError::NotFound(e) => (axum::http::StatusCode::NOT_FOUND, e),
             AppError::InternalServerError(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e),
             AppError::Unauthorized(e) => (axum::http::StatusCode::UNAUTHORIZED, e),
+            AppError::UnprocessableEntity(e) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, e),
         };
 
         let body = Json(ErrorResponse {
@@ -42,6 +44,7 @@ impl Display for AppError {
             AppError::NotFound(e) => write!(f, "Not found: {}", e),
             AppError::InternalServerError(e) => write!(f, "Internal server error: {}", e),
             AppError::Unauthorized(e) => write!(f, "Unauthorized: {}", e),
+            AppError::UnprocessableEntity(e) => write!(f, "Unprocessable entity: {}", e),
         }
     }
 } 
@@ -1,6 +1,8 @@
+use std::sync::Arc;
+
 use tracing_subscriber::FmtSubscriber;
 
-use crate::{config::Config, routes::create_router, streams::{consumer::consume_click_events, get_redis_conn}};
+use crate::{config::Config, enrichment::ClickEnricher, routes::{create_router, AppState}, streams::{consumer::consume_click_events, dedupe::ClickDeduper, get_redis_conn}};
 
 
 mod config;
@@ -11,6 +13,7 @@ mod services;
 mod errors;
 mod validation;
 mod streams;
+mod enrichment;
 #[tokio::main]
 async fn main() {
    //Logger
@@ -20,25 +23,37 @@ async fn main() {
     // Load configuration
     let config = Config::new();
     let addr = format!("0.0.0.0:{}", config.port);
-    let db_url = config.db_url;
 
 // Initialize database connection
-let db_pool = db::connect_db(&db_url).await.expect("Failed to connect to the database");
+let db_pool = db::connect_db(&config.db_url).await.expect("Failed to connect to the database");
 
 tracing::info!("Starting LinkPing on {}", addr);
 
+let enricher = ClickEnricher::new(&config, db_pool.clone());
+let deduper = ClickDeduper::new(&config);
+tokio::spawn(enrichment::geoip::watch_for_changes(enricher.geoip()));
+tokio::spawn(services::rollup::run_rollups(db_pool.clone()));
+tokio::spawn(services::partition::run_partition_maintenance(db_pool.clone()));
+tokio::spawn(services::idempotency::run_key_cleanup(db_pool.clone(), config.idempotency_ttl));
+tokio::spawn(services::conversion::run_pending_cleanup(db_pool.clone()));
+
 //Router
 let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-let app = create_router(db_pool.clone());
+let app = create_router(AppState {
+    db: db_pool.clone(),
+    config: Arc::new(config),
+    redis: get_redis_conn().await.expect("Failed to connect to Redis"),
+});
 
 
 
 let db_pool_for_redis = db_pool.clone();
+// The consumer blocks on XREADGROUP, so it keeps a connection of its own
 let redis_conn = get_redis_conn().await.expect("Failed to connect to Redis");
 
 
 tokio::spawn(async move {
-    if let Err(e) = consume_click_events(&db_pool_for_redis, redis_conn).await {
+    if let Err(e) = consume_click_events(&db_pool_for_redis, redis_conn, enricher, deduper).await {
         tracing::error!("NATS consumer failed: {:?}", e);
     }
 });
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use axum::extract::{FromRef, FromRequestParts};
+
+use crate::{config::Config, errors::AppError};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+const ANONYMOUS: &str = "anonymous";
+
+// Who performed a change, resolved from the X-Api-Key header
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub name: String,
+}
+
+impl Actor {
+    pub fn anonymous() -> Self {
+        Self { name: ANONYMOUS.to_string() }
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.name == ANONYMOUS
+    }
+}
+
+impl<S> FromRequestParts<S> for Actor
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut axum::http::request::Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+
+        let Some(value) = parts.headers.get(API_KEY_HEADER) else {
+            return Ok(Actor::anonymous());
+        };
+
+        let key = value.to_str()
+            .map_err(|_| AppError::Unauthorized("Invalid API key".to_string()))?;
+
+        config.api_keys
+            .get(key)
+            .map(|owner| Actor { name: format!("api_key:{}", owner) })
+            .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))
+    }
+}
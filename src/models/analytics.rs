@@ -1,9 +1,84 @@
 use serde::{Deserialize, Serialize};
 use validator::{Validate, ValidationError};
 use std::ops::RangeInclusive;
-use chrono::NaiveDate;
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
 
-#[derive(Serialize, Deserialize, Debug, Validate)]
+use crate::models::{link::Link, query::QueryMetric};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Minute,
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    // Postgres date_trunc / interval unit
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Granularity::Minute => "minute",
+            Granularity::Hour => "hour",
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+        }
+    }
+
+    pub fn default_buckets(&self) -> i64 {
+        match self {
+            Granularity::Minute => 60,
+            Granularity::Hour => 24,
+            Granularity::Day => 30,
+            Granularity::Week => 12,
+            Granularity::Month => 12,
+        }
+    }
+
+    // One day of minutes, a month of hours, a year of days, two years of weeks or months
+    pub fn max_buckets(&self) -> i64 {
+        match self {
+            Granularity::Minute => 1440,
+            Granularity::Hour => 744,
+            Granularity::Day => 366,
+            Granularity::Week => 104,
+            Granularity::Month => 24,
+        }
+    }
+
+    // Number of buckets between two local dates, both inclusive
+    pub fn bucket_count(&self, start: NaiveDate, end: NaiveDate) -> i64 {
+        let days = (end - start).num_days() + 1;
+        match self {
+            Granularity::Minute => days * 1440,
+            Granularity::Hour => days * 24,
+            Granularity::Day => days,
+            Granularity::Week => {
+                let week_start = |d: NaiveDate| d - chrono::Duration::days(d.weekday().num_days_from_monday() as i64);
+                (week_start(end) - week_start(start)).num_days() / 7 + 1
+            }
+            Granularity::Month => {
+                (end.year() as i64 * 12 + end.month() as i64) - (start.year() as i64 * 12 + start.month() as i64) + 1
+            }
+        }
+    }
+}
+
+// Window the requested range is compared against
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareMode {
+    // The same number of days immediately before the start date
+    PreviousPeriod,
+    // The same dates one year earlier
+    PreviousYear,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Validate)]
 pub struct AnalyticsRequest {
     #[validate(range(min = 1, max = 100, message = "Referer quantity must be between 1 and 100"))]
     pub referer_quantity: Option<i64>,
@@ -11,17 +86,123 @@ pub struct AnalyticsRequest {
     #[validate(range(min = 1, max = 50, message = "User agent quantity must be between 1 and 50"))]
     pub user_agent_quantity: Option<i64>,
     
+    // Number of buckets to return when no start date is given
     #[validate(range(min = 1, max = 365, message = "Click distribution quantity must be between 1 and 365"))]
     pub click_distribution_quantity: Option<i64>,
+
+    pub granularity: Option<Granularity>,
     
     #[validate(custom(function = "validate_date_format", message = "Start date must be in YYYY-MM-DD format"))]
     pub start_date: Option<String>,
     
     #[validate(custom(function = "validate_date_format", message = "End date must be in YYYY-MM-DD format"))]
     pub end_date: Option<String>,
+
+    #[validate(custom(function = "validate_country_code", message = "Country must be a 2-letter ISO code"))]
+    pub country: Option<String>,
+
+    #[validate(custom(function = "validate_timezone", message = "Timezone must be a valid IANA name like 'America/Los_Angeles'"))]
+    pub tz: Option<String>,
+
+    // Bot and prefetch clicks are left out of the counts unless this is set
+    pub include_bots: Option<bool>,
+
+    // Repeat clicks within the dedupe window are left out of the counts unless this is set
+    pub include_duplicates: Option<bool>,
+
+    pub compare: Option<CompareMode>,
 }
 
 impl AnalyticsRequest {
+    // Timezone used for date filters and buckets, UTC unless one is requested
+    pub fn timezone(&self) -> &str {
+        self.tz.as_deref().unwrap_or("UTC")
+    }
+
+    pub fn granularity(&self) -> Granularity {
+        self.granularity.unwrap_or_default()
+    }
+
+    pub fn include_bots(&self) -> bool {
+        self.include_bots.unwrap_or(false)
+    }
+
+    pub fn include_duplicates(&self) -> bool {
+        self.include_duplicates.unwrap_or(false)
+    }
+
+    // Method to keep the click distribution within the bucket limit of its granularity
+    pub fn validate_granularity_range(&self) -> Result<(), ValidationError> {
+        let granularity = self.granularity();
+        let max_buckets = granularity.max_buckets();
+
+        let buckets = match &self.start_date {
+            Some(start_str) => {
+                let today = self.timezone()
+                    .parse::<Tz>()
+                    .map(|tz| Utc::now().with_timezone(&tz).date_naive())
+                    .unwrap_or_else(|_| Utc::now().date_naive());
+                let end = self.end_date
+                    .as_ref()
+                    .and_then(|end_str| NaiveDate::parse_from_str(end_str, "%Y-%m-%d").ok())
+                    .unwrap_or(today);
+
+                match NaiveDate::parse_from_str(start_str, "%Y-%m-%d") {
+                    Ok(start) => granularity.bucket_count(start, end),
+                    // Format errors are reported by the field validators
+                    Err(_) => return Ok(()),
+                }
+            }
+            None => self.click_distribution_quantity.unwrap_or(granularity.default_buckets()),
+        };
+
+        if buckets > max_buckets {
+            let mut err = ValidationError::new("too_many_buckets");
+            err.message = Some(format!(
+                "Range covers {} {} buckets, at most {} are allowed",
+                buckets, granularity.unit(), max_buckets
+            ).into());
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    // A comparison needs an explicit range to shift
+    pub fn validate_comparison(&self) -> Result<(), ValidationError> {
+        if self.compare.is_some() && (self.start_date.is_none() || self.end_date.is_none()) {
+            return Err(ValidationError::new("compare requires both start_date and end_date"));
+        }
+
+        Ok(())
+    }
+
+    // The same request over the comparison window, or None when no comparison was asked for
+    pub fn comparison_request(&self) -> Option<AnalyticsRequest> {
+        let mode = self.compare?;
+        let start = NaiveDate::parse_from_str(self.start_date.as_ref()?, "%Y-%m-%d").ok()?;
+        let end = NaiveDate::parse_from_str(self.end_date.as_ref()?, "%Y-%m-%d").ok()?;
+
+        let (previous_start, previous_end) = match mode {
+            CompareMode::PreviousPeriod => {
+                let previous_end = start.pred_opt()?;
+                (previous_end - (end - start), previous_end)
+            }
+            // Feb 29 falls back to Feb 28
+            CompareMode::PreviousYear => (
+                start.checked_sub_months(Months::new(12))?,
+                end.checked_sub_months(Months::new(12))?,
+            ),
+        };
+
+        Some(AnalyticsRequest {
+            start_date: Some(previous_start.format("%Y-%m-%d").to_string()),
+            end_date: Some(previous_end.format("%Y-%m-%d").to_string()),
+            compare: None,
+            ..self.clone()
+        })
+    }
+
     // Method to validate the relationship between start and end dates
     pub fn validate_date_range(&self) -> Result<(), ValidationError> {
         if let (Some(start_str), Some(end_str)) = (&self.start_date, &self.end_date) {
@@ -49,8 +230,22 @@ impl AnalyticsRequest {
     }
 }
 
+fn validate_country_code(country: &str) -> Result<(), ValidationError> {
+    if country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("Invalid country code"))
+    }
+}
+
+pub fn validate_timezone(tz: &str) -> Result<(), ValidationError> {
+    tz.parse::<Tz>()
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("Unknown timezone"))
+}
+
 // Custom validator for date format (YYYY-MM-DD)
-fn validate_date_format(date: &str) -> Result<(), ValidationError> {
+pub fn validate_date_format(date: &str) -> Result<(), ValidationError> {
     // Check if the date format is YYYY-MM-DD
     if date.len() != 10 {
         return Err(ValidationError::new("Invalid date length"));
@@ -98,25 +293,586 @@ pub struct UserAgentData {
     pub count: i64,
 }
 
+// Click count for one value of a derived dimension (browser, OS, device type, ...)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DimensionData {
+    pub value: String,
+    pub count: i64,
+}
+
+// Clicks per slug the visitor followed; direct clicks report the primary slug
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AliasData {
+    pub alias: String,
+    pub count: i64,
+}
+
+// `timestamp` is the ISO 8601 start of the bucket in the requested timezone
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ClickDistributionData {
-    pub date: String,
+    pub timestamp: String,
     pub count: i64,
 }
 
+// Clicks and the ones among them that converted, for one referrer domain or variant
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConversionData {
+    pub value: String,
+    pub clicks: i64,
+    pub conversions: i64,
+    pub conversion_rate: Option<f64>,
+}
+
+// Share of clicks that converted, rounded to four decimals; None without clicks
+pub fn conversion_rate(conversions: i64, clicks: i64) -> Option<f64> {
+    (clicks > 0).then(|| (conversions as f64 / clicks as f64 * 10_000.0).round() / 10_000.0)
+}
+
+// Clicks by first-time and returning visitors in one bucket of the click distribution
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VisitorDistributionData {
+    pub timestamp: String,
+    pub new: i64,
+    pub returning: i64,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DateRange {
     pub start: String,
     pub end: String,
     pub days: i64,
+    pub timezone: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStatus {
+    Active,
+    Expired,
+}
+
+// The link a slug's analytics belong to; expired links keep their analytics
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LinkMetadata {
+    pub slug: String,
+    pub target_url: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub status: LinkStatus,
+}
+
+impl LinkMetadata {
+    pub fn from_link(link: Link) -> Self {
+        let status = match link.expires_at {
+            Some(expires_at) if expires_at <= Utc::now() => LinkStatus::Expired,
+            _ => LinkStatus::Active,
+        };
+
+        Self {
+            slug: link.slug,
+            target_url: link.target_url,
+            created_at: link.created_at,
+            expires_at: link.expires_at,
+            status,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AnalyticsData {
+    // Set for link analytics, absent for campaigns
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub link: Option<LinkMetadata>,
     pub total_clicks: i64,
     pub unique_clicks: i64,
+    // How unique_clicks is counted; visitor hashes rotate daily, so visitors are counted
+    // once per UTC day and a visitor returning on several days counts once for each
+    pub unique_clicks_scope: String,
+    // Bot clicks in the range, counted whether or not they are included above
+    pub bot_clicks: i64,
+    // Repeat clicks in the range, counted whether or not they are included above;
+    // without include_duplicates the raw click count is total_clicks + duplicate_clicks
+    pub duplicate_clicks: i64,
+    // Clicks by visitors seen on the link for the first time and by ones seen before;
+    // clicks recorded before visitors were tracked count as neither
+    pub new_visitor_clicks: i64,
+    pub returning_visitor_clicks: i64,
+    // Counted clicks whose click id received at least one conversion, for any goal
+    pub conversions: i64,
+    pub conversion_rate: Option<f64>,
     pub top_referrers: Vec<ReferrerData>,
+    pub referrer_domains: Vec<DimensionData>,
+    pub channels: Vec<DimensionData>,
     pub top_user_agents: Vec<UserAgentData>,
+    pub browsers: Vec<DimensionData>,
+    pub browser_versions: Vec<DimensionData>,
+    pub operating_systems: Vec<DimensionData>,
+    pub device_types: Vec<DimensionData>,
+    pub countries: Vec<DimensionData>,
+    pub regions: Vec<DimensionData>,
+    pub cities: Vec<DimensionData>,
+    pub alias_breakdown: Vec<AliasData>,
+    pub bot_reasons: Vec<DimensionData>,
+    pub bots: Vec<DimensionData>,
+    pub referrer_conversions: Vec<ConversionData>,
+    // Per alias, or the primary slug for direct clicks
+    pub variant_conversions: Vec<ConversionData>,
+    pub granularity: Granularity,
     pub click_distribution: Vec<ClickDistributionData>,
+    pub visitor_distribution: Vec<VisitorDistributionData>,
     pub date_range: Option<DateRange>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub comparison: Option<Box<AnalyticsComparison>>,
+}
+
+// Change of a count against the comparison window. `previous` is None for top-N entries
+// that did not make the comparison window's top N, since their count there is unknown.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Delta {
+    pub current: i64,
+    pub previous: Option<i64>,
+    pub change: Option<i64>,
+    // None when the previous count is unknown or zero
+    pub change_percent: Option<f64>,
+}
+
+impl Delta {
+    pub fn new(current: i64, previous: Option<i64>) -> Self {
+        let change_percent = previous
+            .filter(|previous| *previous != 0)
+            .map(|previous| ((current - previous) as f64 / previous as f64 * 10_000.0).round() / 100.0);
+
+        Self {
+            current,
+            previous,
+            change: previous.map(|previous| current - previous),
+            change_percent,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EntryDelta {
+    pub value: String,
+    #[serde(flatten)]
+    pub delta: Delta,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnalyticsDeltas {
+    pub total_clicks: Delta,
+    pub unique_clicks: Delta,
+    pub bot_clicks: Delta,
+    pub duplicate_clicks: Delta,
+    pub new_visitor_clicks: Delta,
+    pub returning_visitor_clicks: Delta,
+    pub conversions: Delta,
+    pub top_referrers: Vec<EntryDelta>,
+    pub referrer_domains: Vec<EntryDelta>,
+    pub channels: Vec<EntryDelta>,
+    pub top_user_agents: Vec<EntryDelta>,
+    pub browsers: Vec<EntryDelta>,
+    pub browser_versions: Vec<EntryDelta>,
+    pub operating_systems: Vec<EntryDelta>,
+    pub device_types: Vec<EntryDelta>,
+    pub countries: Vec<EntryDelta>,
+    pub regions: Vec<EntryDelta>,
+    pub cities: Vec<EntryDelta>,
+    pub alias_breakdown: Vec<EntryDelta>,
+}
+
+impl AnalyticsDeltas {
+    pub fn between(current: &AnalyticsData, previous: &AnalyticsData) -> Self {
+        let dimension = |current: &[DimensionData], previous: &[DimensionData]| {
+            entry_deltas(
+                current.iter().map(|d| (d.value.as_str(), d.count)),
+                previous.iter().map(|d| (d.value.as_str(), d.count)),
+            )
+        };
+
+        Self {
+            total_clicks: Delta::new(current.total_clicks, Some(previous.total_clicks)),
+            unique_clicks: Delta::new(current.unique_clicks, Some(previous.unique_clicks)),
+            bot_clicks: Delta::new(current.bot_clicks, Some(previous.bot_clicks)),
+            duplicate_clicks: Delta::new(current.duplicate_clicks, Some(previous.duplicate_clicks)),
+            new_visitor_clicks: Delta::new(current.new_visitor_clicks, Some(previous.new_visitor_clicks)),
+            returning_visitor_clicks: Delta::new(current.returning_visitor_clicks, Some(previous.returning_visitor_clicks)),
+            conversions: Delta::new(current.conversions, Some(previous.conversions)),
+            top_referrers: entry_deltas(
+                current.top_referrers.iter().map(|r| (r.referer.as_str(), r.count)),
+                previous.top_referrers.iter().map(|r| (r.referer.as_str(), r.count)),
+            ),
+            referrer_domains: dimension(&current.referrer_domains, &previous.referrer_domains),
+            channels: dimension(&current.channels, &previous.channels),
+            top_user_agents: entry_deltas(
+                current.top_user_agents.iter().map(|u| (u.user_agent.as_str(), u.count)),
+                previous.top_user_agents.iter().map(|u| (u.user_agent.as_str(), u.count)),
+            ),
+            browsers: dimension(&current.browsers, &previous.browsers),
+            browser_versions: dimension(&current.browser_versions, &previous.browser_versions),
+            operating_systems: dimension(&current.operating_systems, &previous.operating_systems),
+            device_types: dimension(&current.device_types, &previous.device_types),
+            countries: dimension(&current.countries, &previous.countries),
+            regions: dimension(&current.regions, &previous.regions),
+            cities: dimension(&current.cities, &previous.cities),
+            alias_breakdown: entry_deltas(
+                current.alias_breakdown.iter().map(|a| (a.alias.as_str(), a.count)),
+                previous.alias_breakdown.iter().map(|a| (a.alias.as_str(), a.count)),
+            ),
+        }
+    }
+}
+
+fn entry_deltas<'a>(
+    current: impl Iterator<Item = (&'a str, i64)>,
+    previous: impl Iterator<Item = (&'a str, i64)>,
+) -> Vec<EntryDelta> {
+    let previous: std::collections::HashMap<&str, i64> = previous.collect();
+
+    current
+        .map(|(value, count)| EntryDelta {
+            value: value.to_string(),
+            delta: Delta::new(count, previous.get(value).copied()),
+        })
+        .collect()
+}
+
+// The same metrics over the comparison window, with deltas from it to the requested range
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnalyticsComparison {
+    pub mode: CompareMode,
+    pub previous: AnalyticsData,
+    pub deltas: AnalyticsDeltas,
+}
+
+pub const UNIQUE_CLICKS_SCOPE: &str = "per_day";
+
+// Clicks per local weekday and hour; `matrix[day][hour]` with days Monday first and hours 0-23
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HeatmapData {
+    pub days: Vec<String>,
+    pub matrix: Vec<Vec<i64>>,
+    pub total_clicks: i64,
+    pub date_range: Option<DateRange>,
+    pub timezone: String,
+}
+
+// Clicks and approximate unique visitors (HyperLogLog) over one realtime window
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RealtimeWindow {
+    pub clicks: i64,
+    pub unique_visitors: i64,
+}
+
+// Near-real-time counts served from Redis; `today` is the current UTC calendar day
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RealtimeData {
+    pub slug: String,
+    pub last_minute: RealtimeWindow,
+    pub last_hour: RealtimeWindow,
+    pub last_day: RealtimeWindow,
+    pub today: RealtimeWindow,
+    pub generated_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LiveQuery {
+    // Bot and prefetch clicks are skipped unless this is set
+    pub include_bots: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ExportRequest {
+    pub format: Option<ExportFormat>,
+
+    #[validate(custom(function = "validate_date_format", message = "Start date must be in YYYY-MM-DD format"))]
+    pub start_date: Option<String>,
+
+    #[validate(custom(function = "validate_date_format", message = "End date must be in YYYY-MM-DD format"))]
+    pub end_date: Option<String>,
+
+    #[validate(custom(function = "validate_country_code", message = "Country must be a 2-letter ISO code"))]
+    pub country: Option<String>,
+
+    #[validate(custom(function = "validate_timezone", message = "Timezone must be a valid IANA name like 'America/Los_Angeles'"))]
+    pub tz: Option<String>,
+
+    pub include_bots: Option<bool>,
+
+    pub include_duplicates: Option<bool>,
+}
+
+impl ExportRequest {
+    // The analytics filters that select which clicks are exported
+    pub fn filters(&self) -> AnalyticsRequest {
+        AnalyticsRequest {
+            start_date: self.start_date.clone(),
+            end_date: self.end_date.clone(),
+            country: self.country.clone(),
+            tz: self.tz.clone(),
+            include_bots: self.include_bots,
+            include_duplicates: self.include_duplicates,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct TopLinksRequest {
+    // Ranking metric, clicks unless uniques are requested
+    pub metric: Option<QueryMetric>,
+
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    pub limit: Option<i64>,
+
+    // Bucket size of the sparklines
+    pub granularity: Option<Granularity>,
+
+    #[validate(custom(function = "validate_date_format", message = "Start date must be in YYYY-MM-DD format"))]
+    pub start_date: Option<String>,
+
+    #[validate(custom(function = "validate_date_format", message = "End date must be in YYYY-MM-DD format"))]
+    pub end_date: Option<String>,
+
+    #[validate(custom(function = "validate_timezone", message = "Timezone must be a valid IANA name like 'America/Los_Angeles'"))]
+    pub tz: Option<String>,
+
+    pub include_bots: Option<bool>,
+
+    pub include_duplicates: Option<bool>,
+}
+
+impl TopLinksRequest {
+    const DEFAULT_DAYS: i64 = 7;
+
+    pub fn timezone(&self) -> &str {
+        self.tz.as_deref().unwrap_or("UTC")
+    }
+
+    pub fn metric(&self) -> QueryMetric {
+        self.metric.unwrap_or(QueryMetric::Clicks)
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(10)
+    }
+
+    // Local dates covered, both inclusive; the last seven days up to today by default
+    pub fn range(&self) -> Result<(NaiveDate, NaiveDate), ValidationError> {
+        let today = self.timezone()
+            .parse::<Tz>()
+            .map(|tz| Utc::now().with_timezone(&tz).date_naive())
+            .unwrap_or_else(|_| Utc::now().date_naive());
+        let parse = |date: &Option<String>| {
+            date.as_deref().and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        };
+
+        let end = parse(&self.end_date).unwrap_or(today);
+        let start = parse(&self.start_date).unwrap_or(end - chrono::Duration::days(Self::DEFAULT_DAYS - 1));
+
+        if end < start {
+            return Err(ValidationError::new("End date cannot be before start date"));
+        }
+
+        let granularity = self.granularity.unwrap_or_default();
+        let buckets = granularity.bucket_count(start, end);
+        if buckets > granularity.max_buckets() {
+            let mut err = ValidationError::new("too_many_buckets");
+            err.message = Some(format!(
+                "Range covers {} {} buckets, at most {} are allowed",
+                buckets, granularity.unit(), granularity.max_buckets()
+            ).into());
+            return Err(err);
+        }
+
+        Ok((start, end))
+    }
+}
+
+// One ranked link; `sparkline` holds its clicks per bucket of `TopLinksData::buckets`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TopLink {
+    pub slug: String,
+    pub target_url: String,
+    pub clicks: i64,
+    // Visitors per UTC day, summed over the range, like AnalyticsData::unique_clicks
+    pub uniques: i64,
+    pub sparkline: Vec<i64>,
+}
+
+// Counts over every link the caller owns; `clicks_today` is the current day in the requested timezone
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccountStats {
+    pub total_links: i64,
+    pub active_links: i64,
+    pub expired_links: i64,
+    pub clicks_today: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TopLinksData {
+    pub metric: QueryMetric,
+    pub granularity: Granularity,
+    pub date_range: DateRange,
+    // ISO 8601 bucket starts in the requested timezone, shared by every sparkline
+    pub buckets: Vec<String>,
+    pub links: Vec<TopLink>,
+    pub stats: AccountStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn bucket_count_covers_both_dates() {
+        let day = date("2026-03-10");
+        assert_eq!(Granularity::Minute.bucket_count(day, day), 1440);
+        assert_eq!(Granularity::Hour.bucket_count(day, day), 24);
+        assert_eq!(Granularity::Day.bucket_count(day, date("2026-03-16")), 7);
+    }
+
+    #[test]
+    fn bucket_count_counts_calendar_weeks_and_months() {
+        // Sunday to the following Monday touches two ISO weeks
+        assert_eq!(Granularity::Week.bucket_count(date("2026-03-08"), date("2026-03-09")), 2);
+        assert_eq!(Granularity::Week.bucket_count(date("2026-03-09"), date("2026-03-15")), 1);
+        assert_eq!(Granularity::Month.bucket_count(date("2025-12-31"), date("2026-01-01")), 2);
+        assert_eq!(Granularity::Month.bucket_count(date("2026-01-01"), date("2026-12-31")), 12);
+    }
+
+    #[test]
+    fn granularity_range_is_capped_per_granularity() {
+        let request = |granularity, start: &str, end: &str| AnalyticsRequest {
+            granularity: Some(granularity),
+            start_date: Some(start.to_string()),
+            end_date: Some(end.to_string()),
+            ..Default::default()
+        };
+
+        assert!(request(Granularity::Minute, "2026-03-10", "2026-03-10").validate_granularity_range().is_ok());
+        assert!(request(Granularity::Minute, "2026-03-10", "2026-03-11").validate_granularity_range().is_err());
+        assert!(request(Granularity::Hour, "2026-03-01", "2026-03-31").validate_granularity_range().is_ok());
+        assert!(request(Granularity::Hour, "2026-03-01", "2026-04-01").validate_granularity_range().is_err());
+        assert!(request(Granularity::Day, "2025-01-01", "2025-12-31").validate_granularity_range().is_ok());
+        assert!(request(Granularity::Day, "2025-01-01", "2026-01-01").validate_granularity_range().is_ok());
+        assert!(request(Granularity::Day, "2025-01-01", "2026-01-02").validate_granularity_range().is_err());
+    }
+
+    #[test]
+    fn distribution_quantity_is_limited_without_a_start_date() {
+        let request = |granularity, quantity| AnalyticsRequest {
+            granularity: Some(granularity),
+            click_distribution_quantity: Some(quantity),
+            ..Default::default()
+        };
+
+        assert!(request(Granularity::Day, 365).validate().is_ok());
+        assert!(request(Granularity::Day, 366).validate().is_err());
+        assert!(request(Granularity::Month, 24).validate_granularity_range().is_ok());
+        assert!(request(Granularity::Month, 25).validate_granularity_range().is_err());
+    }
+
+    fn comparison(mode: CompareMode, start: &str, end: &str) -> Option<(String, String)> {
+        let request = AnalyticsRequest {
+            start_date: Some(start.to_string()),
+            end_date: Some(end.to_string()),
+            compare: Some(mode),
+            tz: Some("Europe/Berlin".to_string()),
+            ..Default::default()
+        };
+        let previous = request.comparison_request()?;
+        assert_eq!(previous.compare, None);
+        assert_eq!(previous.tz.as_deref(), Some("Europe/Berlin"));
+        Some((previous.start_date?, previous.end_date?))
+    }
+
+    #[test]
+    fn previous_period_is_the_same_length_right_before_the_start() {
+        assert_eq!(
+            comparison(CompareMode::PreviousPeriod, "2026-03-08", "2026-03-14"),
+            Some(("2026-03-01".to_string(), "2026-03-07".to_string()))
+        );
+        assert_eq!(
+            comparison(CompareMode::PreviousPeriod, "2026-03-01", "2026-03-01"),
+            Some(("2026-02-28".to_string(), "2026-02-28".to_string()))
+        );
+    }
+
+    #[test]
+    fn previous_year_shifts_both_dates_and_clamps_leap_days() {
+        assert_eq!(
+            comparison(CompareMode::PreviousYear, "2026-03-01", "2026-03-31"),
+            Some(("2025-03-01".to_string(), "2025-03-31".to_string()))
+        );
+        assert_eq!(
+            comparison(CompareMode::PreviousYear, "2024-02-01", "2024-02-29"),
+            Some(("2023-02-01".to_string(), "2023-02-28".to_string()))
+        );
+    }
+
+    #[test]
+    fn comparison_needs_a_mode_and_both_dates() {
+        let request = AnalyticsRequest {
+            start_date: Some("2026-03-01".to_string()),
+            ..Default::default()
+        };
+        assert!(request.comparison_request().is_none());
+
+        let request = AnalyticsRequest { compare: Some(CompareMode::PreviousPeriod), ..request };
+        assert!(request.comparison_request().is_none());
+        assert!(request.validate_comparison().is_err());
+    }
+
+    #[test]
+    fn delta_reports_change_and_rounded_percentage() {
+        let delta = Delta::new(150, Some(120));
+        assert_eq!(delta.change, Some(30));
+        assert_eq!(delta.change_percent, Some(25.0));
+
+        assert_eq!(Delta::new(1, Some(3)).change_percent, Some(-66.67));
+        assert_eq!(Delta::new(0, Some(4)).change, Some(-4));
+    }
+
+    #[test]
+    fn delta_without_a_usable_previous_count_has_no_percentage() {
+        let unknown = Delta::new(7, None);
+        assert_eq!(unknown.change, None);
+        assert_eq!(unknown.change_percent, None);
+
+        let from_zero = Delta::new(7, Some(0));
+        assert_eq!(from_zero.change, Some(7));
+        assert_eq!(from_zero.change_percent, None);
+    }
 }
@@ -2,6 +2,11 @@ use axum::{extract::FromRequestParts, http::StatusCode};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
+// First-party cookie identifying a browser across clicks, used to tell new visitors from returning ones
+pub const VISITOR_COOKIE: &str = "lp_vid";
+// One year; the cookie is only set when missing, so visitors keyed by it are forgotten after this
+pub const VISITOR_COOKIE_MAX_AGE_SECS: u64 = 365 * 24 * 3600;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ClickEvent{
     pub slug : String,
@@ -9,6 +14,79 @@ pub struct ClickEvent{
     pub user_agent: String,
     pub referer : Option<String>,
     pub timestamp : chrono::DateTime<Utc>,
+    // Alias the visitor followed, when `slug` was reached through one
+    #[serde(default)]
+    pub alias : Option<String>,
+    // Prefetch hint from Sec-Purpose, Purpose, X-Purpose or X-Moz, used for bot detection
+    #[serde(default)]
+    pub purpose : Option<String>,
+    #[serde(default)]
+    pub accept_language : Option<String>,
+    // Visitor cookie sent with the click, or the one issued with its response
+    #[serde(default)]
+    pub visitor_cookie : Option<String>,
+    // Whether `visitor_cookie` was issued by this click rather than sent by the browser
+    #[serde(default)]
+    pub visitor_cookie_issued : bool,
+    // Id appended to the destination URL, which conversions refer back to
+    #[serde(default)]
+    pub click_id : Option<String>,
+}
+
+// Attributes the stream consumer derives from a ClickEvent before storing it
+#[derive(Debug)]
+pub struct ClickEnrichment {
+    // IP in the configured storage form (raw, truncated or none)
+    pub ip : Option<String>,
+    pub visitor_id : Option<String>,
+    pub browser_family : String,
+    pub browser_version : Option<String>,
+    pub os_family : String,
+    pub device_type : String,
+    pub referrer_domain : Option<String>,
+    pub channel : String,
+    pub country : Option<String>,
+    pub region : Option<String>,
+    pub city : Option<String>,
+    pub asn : Option<i64>,
+    pub asn_org : Option<String>,
+    pub is_bot : bool,
+    pub bot_reason : Option<String>,
+    pub bot_name : Option<String>,
+    // Set by the consumer when the visitor clicked the same link within the dedupe window
+    pub is_duplicate : bool,
+    // None when the visitor could not be looked up
+    pub is_returning : Option<bool>,
+}
+
+// A stored click as exported to CSV, JSON Lines and Parquet
+#[derive(Serialize, Debug, sqlx::FromRow)]
+pub struct ClickRecord {
+    pub id : i64,
+    pub slug : String,
+    pub alias : Option<String>,
+    pub timestamp : chrono::DateTime<Utc>,
+    pub ip : Option<String>,
+    pub visitor_id : Option<String>,
+    pub user_agent : Option<String>,
+    pub referer : Option<String>,
+    pub referrer_domain : Option<String>,
+    pub channel : Option<String>,
+    pub browser_family : Option<String>,
+    pub browser_version : Option<String>,
+    pub os_family : Option<String>,
+    pub device_type : Option<String>,
+    pub country : Option<String>,
+    pub region : Option<String>,
+    pub city : Option<String>,
+    pub asn : Option<i64>,
+    pub asn_org : Option<String>,
+    pub is_bot : bool,
+    pub bot_reason : Option<String>,
+    pub bot_name : Option<String>,
+    pub is_duplicate : bool,
+    pub is_returning : Option<bool>,
+    pub click_id : Option<String>,
 }
 
 impl<S> FromRequestParts<S> for ClickEvent
@@ -28,6 +106,25 @@ where
                 .and_then(|v| v.to_str().ok())
                 .map(|s| s.to_string());
 
+            let purpose = ["sec-purpose", "purpose", "x-purpose", "x-moz"]
+                .iter()
+                .find_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()))
+                .map(|s| s.to_string());
+
+            let accept_language = headers.get("accept-language")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+
+            // Only ids we could have issued are accepted
+            let visitor_cookie = headers.get_all("cookie")
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(';'))
+                .filter_map(|pair| pair.trim().split_once('='))
+                .find(|(name, _)| *name == VISITOR_COOKIE)
+                .map(|(_, value)| value.to_string())
+                .filter(|value| value.len() == 32 && value.chars().all(|c| c.is_ascii_hexdigit()));
+
             let ip = parts
                 .extensions
                 .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
@@ -48,6 +145,12 @@ where
                 user_agent,
                 referer,
                 timestamp,
+                alias: None,
+                purpose,
+                accept_language,
+                visitor_cookie,
+                visitor_cookie_issued: false,
+                click_id: None,
             }) 
         }
     }
\ No newline at end of file
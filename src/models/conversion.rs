@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+// Query parameter carrying the click id on the destination URL
+pub const CLICK_ID_PARAM: &str = "lp_click_id";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversionSource {
+    // The /convert image loaded by the visitor's browser
+    Pixel,
+    // A server-to-server call to /conversions
+    Postback,
+}
+
+impl ConversionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConversionSource::Pixel => "pixel",
+            ConversionSource::Postback => "postback",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ConversionRequest {
+    #[validate(custom(function = "validate_click_id", message = "Click id must be the 32 character id from the destination URL"))]
+    pub click_id: String,
+
+    // What the visitor did, like 'signup' or 'purchase'; 'default' when not given
+    #[validate(custom(function = "validate_goal", message = "Goal must be between 1 and 64 characters"))]
+    pub goal: Option<String>,
+}
+
+impl ConversionRequest {
+    pub fn goal(&self) -> &str {
+        self.goal.as_deref().map(str::trim).unwrap_or("default")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
+pub struct Conversion {
+    pub id: i64,
+    pub click_id: String,
+    pub goal: String,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+fn validate_click_id(click_id: &str) -> Result<(), ValidationError> {
+    if click_id.len() == 32 && click_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("Invalid click id"))
+    }
+}
+
+// Checked on the trimmed value `goal()` records, so blank goals are rejected
+fn validate_goal(goal: &str) -> Result<(), ValidationError> {
+    let length = goal.trim().chars().count();
+    if (1..=64).contains(&length) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("Invalid goal"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(goal: Option<&str>) -> ConversionRequest {
+        ConversionRequest {
+            click_id: "0123456789abcdef0123456789abcdef".to_string(),
+            goal: goal.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn goal_defaults_and_is_trimmed() {
+        assert_eq!(request(None).goal(), "default");
+        assert_eq!(request(Some("  signup ")).goal(), "signup");
+    }
+
+    #[test]
+    fn blank_or_long_goals_are_rejected() {
+        assert!(request(Some("signup")).validate().is_ok());
+        assert!(request(Some("   ")).validate().is_err());
+        assert!(request(Some("")).validate().is_err());
+        assert!(request(Some(&"g".repeat(65))).validate().is_err());
+    }
+
+    #[test]
+    fn click_id_must_be_32_hex_characters() {
+        let mut short = request(None);
+        short.click_id = "abc".to_string();
+        assert!(short.validate().is_err());
+
+        let mut not_hex = request(None);
+        not_hex.click_id = "z".repeat(32);
+        assert!(not_hex.validate().is_err());
+    }
+}
@@ -1,6 +1,7 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 use validator::Validate;
-use crate::validation::url::{validate_scheme, validate_expiry};
+use crate::validation::{slug::validate_slug_name, tags::validate_tags, url::{validate_scheme, validate_expiry}};
 
 #[derive(Serialize, Deserialize, Validate)]
 pub struct ShortenRequest{
@@ -11,17 +12,167 @@ pub struct ShortenRequest{
      ))]
     pub target_url : String,
     #[validate(length(min = 3, max = 20))]
+    #[validate(custom(function = "validate_slug_name", message = "This name is reserved for an API route"))]
     pub custom_slug: Option<String>,
 
     #[validate(custom(
         function = "validate_expiry",
         message = "Expiry must be a valid duration like '1d', '6h', '30m'"
     ))]
-    pub expires_in: Option<String>
+    pub expires_in: Option<String>,
+
+    #[validate(custom(
+        function = "validate_tags",
+        message = "Up to 10 tags of 1-32 letters, digits, '-' or '_' are allowed"
+    ))]
+    pub tags: Option<Vec<String>>,
+
+    pub campaign_id: Option<i32>,
+
+    // Appends the click id to the destination so conversions can be reported; off by default
+    // because destinations like presigned URLs reject unknown query parameters
+    pub track_conversions: Option<bool>,
+}
+
+// Fields left out of the body are unchanged; `null` clears expiry or campaign
+#[derive(Deserialize, Validate)]
+pub struct UpdateLinkRequest {
+    #[validate(url)]
+    #[validate(custom(
+        function = "validate_scheme",
+        message = "Invalid URL scheme. Only http and https are allowed."
+    ))]
+    pub target_url: Option<String>,
+
+    #[serde(default, deserialize_with = "double_option")]
+    pub expires_in: Option<Option<String>>,
+
+    #[validate(custom(
+        function = "validate_tags",
+        message = "Up to 10 tags of 1-32 letters, digits, '-' or '_' are allowed"
+    ))]
+    pub tags: Option<Vec<String>>,
+
+    #[serde(default, deserialize_with = "double_option")]
+    pub campaign_id: Option<Option<i32>>,
+
+    pub track_conversions: Option<bool>,
+}
+
+// Distinguishes an explicit `null` (Some(None)) from a missing field (None)
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct RollbackRequest {
+    #[validate(range(min = 1, message = "Revision must be positive"))]
+    pub revision: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
+pub struct LinkRevision {
+    pub slug: String,
+    pub revision: i32,
+    pub action: String,
+    pub target_url: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub campaign_id: Option<i32>,
+    pub track_conversions: bool,
+    pub source_revision: Option<i32>,
+    pub actor: String,
+    pub created_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Validate)]
+pub struct LinkListQuery {
+    pub tag: Option<String>,
+    pub campaign_id: Option<i32>,
+
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    pub limit: Option<i64>,
+
+    #[validate(range(min = 0, message = "Offset cannot be negative"))]
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct CreateAliasRequest {
+    #[validate(length(min = 3, max = 20))]
+    #[validate(custom(function = "validate_slug_name", message = "This name is reserved for an API route"))]
+    pub alias: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
+pub struct LinkAlias {
+    pub alias: String,
+    pub slug: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// Result of resolving a requested slug, which may be an alias of the primary link
+#[derive(Debug)]
+pub struct ResolvedLink {
+    pub slug: String,
+    pub alias: Option<String>,
+    pub target_url: String,
+    pub track_conversions: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Link {
+    pub slug: String,
+    pub target_url: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub campaign_id: Option<i32>,
+    pub track_conversions: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LinkResources {
+    pub analytics: String,
+    pub qr: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct ShortenResponse {
     pub slug: String,
+    pub short_url: String,
+    pub target_url: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub campaign_id: Option<i32>,
+    #[serde(default)]
+    pub track_conversions: bool,
+    pub links: LinkResources,
+}
+
+impl ShortenResponse {
+    pub fn from_link(link: Link, base_url: &str) -> Self {
+        Self {
+            short_url: format!("{}/{}", base_url, link.slug),
+            links: LinkResources {
+                analytics: format!("{}/analytics/{}", base_url, link.slug),
+                qr: format!("{}/links/{}/qr", base_url, link.slug),
+            },
+            slug: link.slug,
+            target_url: link.target_url,
+            created_at: link.created_at,
+            expires_at: link.expires_at,
+            tags: link.tags,
+            campaign_id: link.campaign_id,
+            track_conversions: link.track_conversions,
+        }
+    }
 }
 
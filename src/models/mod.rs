@@ -1,3 +1,7 @@
 pub mod link;
 pub mod click;
-pub mod analytics;
\ No newline at end of file
+pub mod analytics;
+pub mod campaign;
+pub mod actor;
+pub mod query;
+pub mod conversion;
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::analytics::{validate_date_format, validate_timezone, Granularity};
+
+// Columns a query may group and filter by; anything else is rejected at deserialization
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryDimension {
+    Slug,
+    // The alias (or the primary slug) the visitor followed
+    Variant,
+    ReferrerDomain,
+    Channel,
+    Browser,
+    Os,
+    Device,
+    Country,
+    // Start of the `granularity` bucket in the requested timezone
+    Date,
+}
+
+impl QueryDimension {
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueryDimension::Slug => "slug",
+            QueryDimension::Variant => "variant",
+            QueryDimension::ReferrerDomain => "referrer_domain",
+            QueryDimension::Channel => "channel",
+            QueryDimension::Browser => "browser",
+            QueryDimension::Os => "os",
+            QueryDimension::Device => "device",
+            QueryDimension::Country => "country",
+            QueryDimension::Date => "date",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMetric {
+    Clicks,
+    Uniques,
+}
+
+impl QueryMetric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueryMetric::Clicks => "clicks",
+            QueryMetric::Uniques => "uniques",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOperator {
+    Eq,
+    Neq,
+    In,
+    NotIn,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum FilterValue {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl FilterValue {
+    pub fn values(&self) -> Vec<&str> {
+        match self {
+            FilterValue::One(value) => vec![value.as_str()],
+            FilterValue::Many(values) => values.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryFilter {
+    pub dimension: QueryDimension,
+    pub op: FilterOperator,
+    pub value: FilterValue,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryOrder {
+    // A requested dimension or metric name
+    pub field: String,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct AnalyticsQuery {
+    // Links to aggregate over; all of the caller's links when neither slugs nor a campaign is given
+    #[validate(length(max = 100, message = "At most 100 slugs can be queried at once"))]
+    pub slugs: Option<Vec<String>>,
+    pub campaign_id: Option<i32>,
+
+    #[serde(default)]
+    #[validate(length(max = 4, message = "At most 4 dimensions can be combined"))]
+    pub dimensions: Vec<QueryDimension>,
+
+    #[validate(length(min = 1, max = 2, message = "Between 1 and 2 metrics must be requested"))]
+    pub metrics: Vec<QueryMetric>,
+
+    #[serde(default)]
+    #[validate(length(max = 10, message = "At most 10 filters are allowed"))]
+    pub filters: Vec<QueryFilter>,
+
+    #[serde(default)]
+    pub order_by: Vec<QueryOrder>,
+
+    #[validate(range(min = 1, max = 1000, message = "Limit must be between 1 and 1000"))]
+    pub limit: Option<i64>,
+
+    pub granularity: Option<Granularity>,
+
+    #[validate(custom(function = "validate_date_format", message = "Start date must be in YYYY-MM-DD format"))]
+    pub start_date: Option<String>,
+
+    #[validate(custom(function = "validate_date_format", message = "End date must be in YYYY-MM-DD format"))]
+    pub end_date: Option<String>,
+
+    #[validate(custom(function = "validate_timezone", message = "Timezone must be a valid IANA name like 'America/Los_Angeles'"))]
+    pub tz: Option<String>,
+
+    pub include_bots: Option<bool>,
+
+    pub include_duplicates: Option<bool>,
+}
+
+impl AnalyticsQuery {
+    pub fn timezone(&self) -> &str {
+        self.tz.as_deref().unwrap_or("UTC")
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(100)
+    }
+}
+
+// One object per row, keyed by dimension and metric names
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QueryResult {
+    pub dimensions: Vec<QueryDimension>,
+    pub metrics: Vec<QueryMetric>,
+    pub timezone: String,
+    pub rows: Vec<serde_json::Map<String, serde_json::Value>>,
+}
@@ -1,16 +1,35 @@
+use std::convert::Infallible;
+
 use axum::{
+    body::Body,
     extract::{Path, State, Query},
-    http::StatusCode,
+    http::{header, StatusCode},
+    response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response},
     Json,
 };
+use futures::{Stream, StreamExt};
+use redis::aio::MultiplexedConnection;
 use validator::Validate;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     errors::AppError,
-    services::analytics::get_analytics_data,
-    models::analytics::{AnalyticsRequest, AnalyticsData},
+    services::{
+        analytics::{get_analytics_data, get_campaign_analytics, get_campaign_heatmap, get_link_heatmap},
+        campaign::get_campaign,
+        export::{analytics_csv, export_clicks},
+        leaderboard::get_top_links,
+        link::get_owned_link,
+        query::run_query,
+        realtime::get_realtime_data,
+    },
+    models::{
+        actor::Actor,
+        analytics::{AnalyticsRequest, AnalyticsData, ExportRequest, HeatmapData, LiveQuery, RealtimeData, TopLinksData, TopLinksRequest},
+        query::{AnalyticsQuery, QueryResult},
+    },
+    streams::live::subscribe_live_clicks,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -30,9 +49,234 @@ pub struct ErrorResponse {
 
 pub async fn analytics_handler(
     State(db): State<sqlx::PgPool>,
+    actor: Actor,
     Path(slug): Path<String>,
     Query(params): Query<AnalyticsRequest>,
 ) -> Result<Json<ApiResponse<AnalyticsData>>, (StatusCode, Json<ErrorResponse>)> {
+    // Analytics are only shown to the owner of the link
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required for analytics".to_string())));
+    }
+
+    validate_analytics_request(&params)?;
+
+    let slug = get_owned_link(&db, &slug, &actor.name).await.map_err(error_response)?.slug;
+
+    // Get analytics data with query parameters
+    match get_analytics_data(&db, slug, &params).await {
+        Ok(analytics_data) => Ok(success_response(analytics_data)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+pub async fn campaign_analytics_handler(
+    State(db): State<sqlx::PgPool>,
+    Path(campaign_id): Path<i32>,
+    actor: Actor,
+    Query(params): Query<AnalyticsRequest>,
+) -> Result<Json<ApiResponse<AnalyticsData>>, (StatusCode, Json<ErrorResponse>)> {
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required for analytics".to_string())));
+    }
+
+    validate_analytics_request(&params)?;
+
+    // Make sure the campaign exists and belongs to the caller before aggregating its links
+    get_campaign(&db, campaign_id, &actor.name).await.map_err(error_response)?;
+
+    match get_campaign_analytics(&db, campaign_id, &params).await {
+        Ok(analytics_data) => Ok(success_response(analytics_data)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+pub async fn heatmap_handler(
+    State(db): State<sqlx::PgPool>,
+    actor: Actor,
+    Path(slug): Path<String>,
+    Query(params): Query<AnalyticsRequest>,
+) -> Result<Json<ApiResponse<HeatmapData>>, (StatusCode, Json<ErrorResponse>)> {
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required for analytics".to_string())));
+    }
+
+    validate_analytics_request(&params)?;
+
+    let slug = get_owned_link(&db, &slug, &actor.name).await.map_err(error_response)?.slug;
+
+    match get_link_heatmap(&db, slug, &params).await {
+        Ok(heatmap) => Ok(success_response(heatmap)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+pub async fn campaign_heatmap_handler(
+    State(db): State<sqlx::PgPool>,
+    Path(campaign_id): Path<i32>,
+    actor: Actor,
+    Query(params): Query<AnalyticsRequest>,
+) -> Result<Json<ApiResponse<HeatmapData>>, (StatusCode, Json<ErrorResponse>)> {
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required for analytics".to_string())));
+    }
+
+    validate_analytics_request(&params)?;
+
+    get_campaign(&db, campaign_id, &actor.name).await.map_err(error_response)?;
+
+    match get_campaign_heatmap(&db, campaign_id, &params).await {
+        Ok(heatmap) => Ok(success_response(heatmap)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+pub async fn realtime_analytics_handler(
+    State(db): State<sqlx::PgPool>,
+    State(mut redis): State<MultiplexedConnection>,
+    actor: Actor,
+    Path(slug): Path<String>,
+) -> Result<Json<ApiResponse<RealtimeData>>, (StatusCode, Json<ErrorResponse>)> {
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required for analytics".to_string())));
+    }
+
+    // Counters are kept under the primary slug; links of other owners are reported as missing
+    let slug = get_owned_link(&db, &slug, &actor.name).await.map_err(error_response)?.slug;
+
+    match get_realtime_data(&mut redis, slug).await {
+        Ok(realtime_data) => Ok(success_response(realtime_data)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+pub async fn live_analytics_handler(
+    State(db): State<sqlx::PgPool>,
+    actor: Actor,
+    Path(slug): Path<String>,
+    Query(params): Query<LiveQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<ErrorResponse>)> {
+    // The live feed is only available to API key holders
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required for live analytics".to_string())));
+    }
+
+    let slug = get_owned_link(&db, &slug, &actor.name).await.map_err(error_response)?.slug;
+
+    let clicks = subscribe_live_clicks(&slug).await
+        .map_err(|e| error_response(AppError::InternalServerError(format!("Live subscription: {}", e))))?;
+
+    let include_bots = params.include_bots.unwrap_or(false);
+    let events = clicks
+        .filter(move |click| std::future::ready(include_bots || !click.is_bot))
+        .filter_map(|click| async move {
+            match Event::default().event("click").json_data(&click) {
+                Ok(event) => Some(Ok(event)),
+                Err(e) => {
+                    tracing::warn!("Failed to encode live click: {:?}", e);
+                    None
+                }
+            }
+        });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+pub async fn export_handler(
+    State(db): State<sqlx::PgPool>,
+    actor: Actor,
+    Path(slug): Path<String>,
+    Query(params): Query<ExportRequest>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    // Raw clicks carry IPs and user agents, so they are only exported to API key holders
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required to export clicks".to_string())));
+    }
+
+    params.validate()
+        .map_err(|e| error_response(AppError::ValidationError(format!("Validation error: {}", e))))?;
+    let filters = params.filters();
+    filters.validate_date_range()
+        .map_err(|e| error_response(AppError::ValidationError(format!("Date validation error: {}", e))))?;
+
+    let slug = get_owned_link(&db, &slug, &actor.name).await.map_err(error_response)?.slug;
+
+    let format = params.format.unwrap_or_default();
+    let filename = format!("{}-clicks.{}", slug, format.extension());
+    let body = Body::from_stream(export_clicks(db, slug, filters, format).await);
+
+    Ok(attachment(format.content_type(), &filename, body))
+}
+
+pub async fn export_summary_handler(
+    State(db): State<sqlx::PgPool>,
+    actor: Actor,
+    Path(slug): Path<String>,
+    Query(params): Query<AnalyticsRequest>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    // Exports follow the raw click export: API key holders, for their own links
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required to export analytics".to_string())));
+    }
+
+    validate_analytics_request(&params)?;
+
+    let slug = get_owned_link(&db, &slug, &actor.name).await.map_err(error_response)?.slug;
+    let analytics_data = get_analytics_data(&db, slug.clone(), &params).await.map_err(error_response)?;
+    let csv = analytics_csv(&analytics_data).map_err(error_response)?;
+
+    Ok(attachment("text/csv; charset=utf-8", &format!("{}-analytics.csv", slug), Body::from(csv)))
+}
+
+pub async fn query_handler(
+    State(db): State<sqlx::PgPool>,
+    actor: Actor,
+    Json(query): Json<AnalyticsQuery>,
+) -> Result<Json<ApiResponse<QueryResult>>, (StatusCode, Json<ErrorResponse>)> {
+    // Queries can span every link of the caller, so they are limited to API key holders
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required for analytics queries".to_string())));
+    }
+
+    query.validate()
+        .map_err(|e| error_response(AppError::ValidationError(format!("Validation error: {}", e))))?;
+
+    match run_query(&db, &actor.name, &query).await {
+        Ok(result) => Ok(success_response(result)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+pub async fn top_links_handler(
+    State(db): State<sqlx::PgPool>,
+    actor: Actor,
+    Query(params): Query<TopLinksRequest>,
+) -> Result<Json<ApiResponse<TopLinksData>>, (StatusCode, Json<ErrorResponse>)> {
+    // The leaderboard covers the links created with the caller's API key
+    if actor.is_anonymous() {
+        return Err(error_response(AppError::Unauthorized("An API key is required for the link leaderboard".to_string())));
+    }
+
+    params.validate()
+        .map_err(|e| error_response(AppError::ValidationError(format!("Validation error: {}", e))))?;
+
+    match get_top_links(&db, &actor.name, &params).await {
+        Ok(top_links) => Ok(success_response(top_links)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+fn attachment(content_type: &str, filename: &str, body: Body) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn validate_analytics_request(params: &AnalyticsRequest) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
     // Validate the request parameters
     if let Err(e) = params.validate() {
         let error_response = ErrorResponse {
@@ -54,34 +298,54 @@ pub async fn analytics_handler(
         };
         return Err((StatusCode::BAD_REQUEST, Json(error_response)));
     }
-    
-    // Get analytics data with query parameters
-    match get_analytics_data(&db, slug, &params).await {
-        Ok(analytics_data) => {
-            let response = ApiResponse {
-                success: true,
-                timestamp: Utc::now().to_rfc3339(),
-                data: analytics_data,
-            };
-            Ok(Json(response))
-        },
-        Err(e) => {
-            let status_code = match &e {
-                AppError::NotFound(_) => StatusCode::NOT_FOUND,
-                AppError::ValidationError(_) => StatusCode::BAD_REQUEST,
-                AppError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                AppError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
-            };
-            
-            let error_response = ErrorResponse {
-                success: false,
-                timestamp: Utc::now().to_rfc3339(),
-                error: e.to_string(),
-                status_code: status_code.as_u16(),
-            };
-            
-            Err((status_code, Json(error_response)))
-        }
+
+    if let Err(e) = params.validate_comparison() {
+        let error_response = ErrorResponse {
+            success: false,
+            timestamp: Utc::now().to_rfc3339(),
+            error: format!("Comparison validation error: {}", e),
+            status_code: StatusCode::BAD_REQUEST.as_u16(),
+        };
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+    }
+
+    if let Err(e) = params.validate_granularity_range() {
+        let error_response = ErrorResponse {
+            success: false,
+            timestamp: Utc::now().to_rfc3339(),
+            error: format!("Granularity validation error: {}", e),
+            status_code: StatusCode::BAD_REQUEST.as_u16(),
+        };
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
     }
+
+    Ok(())
+}
+
+fn success_response<T>(data: T) -> Json<ApiResponse<T>> {
+    Json(ApiResponse {
+        success: true,
+        timestamp: Utc::now().to_rfc3339(),
+        data,
+    })
+}
+
+fn error_response(e: AppError) -> (StatusCode, Json<ErrorResponse>) {
+    let status_code = match &e {
+        AppError::NotFound(_) => StatusCode::NOT_FOUND,
+        AppError::ValidationError(_) => StatusCode::BAD_REQUEST,
+        AppError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        AppError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        AppError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+    };
+    
+    let error_response = ErrorResponse {
+        success: false,
+        timestamp: Utc::now().to_rfc3339(),
+        error: e.to_string(),
+        status_code: status_code.as_u16(),
+    };
+    
+    (status_code, Json(error_response))
 }
@@ -0,0 +1,57 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use validator::Validate;
+
+use crate::{
+    errors::AppError,
+    models::{actor::Actor, campaign::{Campaign, CreateCampaignRequest}},
+    routes::AppState,
+    services::campaign::{create_campaign, get_campaign, list_campaigns},
+};
+
+pub async fn create_campaign_handler(
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(payload): Json<CreateCampaignRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_api_key(&actor)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(AppError::ValidationError(e.to_string()));
+    }
+
+    let campaign = create_campaign(&state.db, payload.name, payload.description, &actor.name).await?;
+    let location = format!("{}/campaigns/{}", state.config.public_base_url, campaign.id);
+
+    Ok((StatusCode::CREATED, [(header::LOCATION, location)], Json(campaign)))
+}
+
+pub async fn list_campaigns_handler(
+    State(db): State<sqlx::PgPool>,
+    actor: Actor,
+) -> Result<Json<Vec<Campaign>>, AppError> {
+    require_api_key(&actor)?;
+
+    Ok(Json(list_campaigns(&db, &actor.name).await?))
+}
+
+pub async fn get_campaign_handler(
+    State(db): State<sqlx::PgPool>,
+    Path(campaign_id): Path<i32>,
+    actor: Actor,
+) -> Result<Json<Campaign>, AppError> {
+    require_api_key(&actor)?;
+
+    Ok(Json(get_campaign(&db, campaign_id, &actor.name).await?))
+}
+
+// Campaigns belong to the owner of an API key
+fn require_api_key(actor: &Actor) -> Result<(), AppError> {
+    if actor.is_anonymous() {
+        return Err(AppError::Unauthorized("An API key is required to manage campaigns".to_string()));
+    }
+    Ok(())
+}
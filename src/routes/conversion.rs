@@ -0,0 +1,66 @@
+use axum::{
+    extract::{rejection::QueryRejection, Json, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use validator::Validate;
+
+use crate::{
+    errors::AppError,
+    models::{actor::Actor, conversion::ConversionRequest},
+    services::conversion::{record_pixel_conversion, record_postback_conversion},
+};
+
+// 1x1 transparent GIF
+const PIXEL_GIF: [u8; 43] = [
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+// Embedded on the destination's thank-you page; the image is served even when nothing is
+// recorded, so a bad or missing click id never shows up as a broken image
+pub async fn convert_pixel_handler(
+    State(db): State<sqlx::PgPool>,
+    params: Result<Query<ConversionRequest>, QueryRejection>,
+) -> impl IntoResponse {
+    match params {
+        Ok(Query(params)) => match params.validate() {
+            Ok(()) => {
+                if let Err(e) = record_pixel_conversion(&db, &params.click_id, params.goal()).await {
+                    tracing::error!("Failed to record pixel conversion for click {}: {:?}", params.click_id, e);
+                }
+            }
+            Err(e) => tracing::debug!("Ignoring conversion pixel: {}", e),
+        },
+        Err(e) => tracing::debug!("Ignoring conversion pixel: {}", e),
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (header::CACHE_CONTROL, "no-store, no-cache, must-revalidate"),
+        ],
+        PIXEL_GIF,
+    )
+}
+
+pub async fn postback_handler(
+    State(db): State<sqlx::PgPool>,
+    actor: Actor,
+    Json(payload): Json<ConversionRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    // Postbacks come from the advertiser's backend, which holds an API key
+    if actor.is_anonymous() {
+        return Err(AppError::Unauthorized("An API key is required for conversion postbacks".to_string()));
+    }
+
+    if let Err(e) = payload.validate() {
+        return Err(AppError::ValidationError(e.to_string()));
+    }
+
+    let (conversion, created) = record_postback_conversion(&db, &payload.click_id, payload.goal(), &actor.name).await?;
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+
+    Ok((status, Json(conversion)))
+}
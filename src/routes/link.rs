@@ -1,35 +1,224 @@
+use std::sync::Arc;
+
 use axum::{
-    extract::{ Json, State}, routing::{get, post}, Router
+    extract::{ Json, Query, State}, http::{header, HeaderMap, StatusCode}, response::IntoResponse
+};
+use qrcode::{render::svg, QrCode};
+use rand::Rng;
+use redis::aio::MultiplexedConnection;
+use crate::{config::Config, models::{actor::Actor, click::{ClickEvent, VISITOR_COOKIE, VISITOR_COOKIE_MAX_AGE_SECS}, conversion::CLICK_ID_PARAM, link::{CreateAliasRequest, LinkAlias, LinkListQuery, LinkRevision, RollbackRequest, ShortenRequest, ShortenResponse, UpdateLinkRequest}}, routes::AppState, streams::producer::publish_click_event};
+use crate::services::{
+    idempotency,
+    link::{create_alias, create_short_link, delete_alias, get_link, get_owned_link, list_aliases, list_links, rollback_link, update_link},
+    realtime::record_click,
+    revision::list_revisions,
 };
-use crate::{models::{click::ClickEvent, link::{ShortenRequest, ShortenResponse}}, routes::analytics, streams::producer::publish_click_event};
-use crate::services::link::create_short_link;
+use crate::enrichment::bot::classify_bot;
 use crate::errors::AppError;
 use validator::Validate;
 
-
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
 
 pub async fn shorten_handler(
-    State(db) : State<sqlx::PgPool>,
+    State(state) : State<AppState>,
+    headers: HeaderMap,
+    actor: Actor,
     Json(payload): Json<ShortenRequest>,
-) -> Result<Json<ShortenResponse>, AppError> { 
+) -> Result<impl IntoResponse, AppError> { 
+
+    if let Err(e) = payload.validate() {
+        return Err(AppError::ValidationError(e.to_string()));
+    }
+
+    let idempotency_key = match headers.get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => {
+            let key = value.to_str()
+                .map_err(|_| AppError::ValidationError("Idempotency-Key must be valid ASCII".to_string()))?;
+            if key.is_empty() || key.len() > idempotency::MAX_KEY_LENGTH {
+                return Err(AppError::ValidationError(format!(
+                    "Idempotency-Key must be between 1 and {} characters",
+                    idempotency::MAX_KEY_LENGTH
+                )));
+            }
+            Some((key.to_string(), idempotency::request_fingerprint(&actor.name, &payload)?))
+        }
+        None => None,
+    };
+
+    let mut tx = state.db.begin().await?;
+
+    // Replay the original response for a retried request
+    if let Some((key, fingerprint)) = &idempotency_key {
+        let ttl = state.config.idempotency_ttl;
+        if let Some(response) = idempotency::reserve_key(&mut tx, &actor.name, key, fingerprint, ttl).await? {
+            return Ok(created_response(&state, response, Some(true)));
+        }
+    }
+
+    let link = create_short_link(&mut tx, payload, &actor).await?;
+    let response = ShortenResponse::from_link(link, &state.config.public_base_url);
+
+    if let Some((key, _)) = &idempotency_key {
+        idempotency::store_response(&mut tx, &actor.name, key, &response).await?;
+    }
+    tx.commit().await?;
+
+    Ok(created_response(&state, response, idempotency_key.is_some().then_some(false)))
+}
+
+// `replayed` is only reported for requests that carried an Idempotency-Key
+fn created_response(state: &AppState, response: ShortenResponse, replayed: Option<bool>) -> axum::response::Response {
+    let location = format!("{}/links/{}", state.config.public_base_url, response.slug);
+
+    let mut res = (StatusCode::CREATED, [(header::LOCATION, location)], Json(response)).into_response();
+    if let Some(replayed) = replayed {
+        res.headers_mut().insert(
+            header::HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER),
+            header::HeaderValue::from_static(if replayed { "true" } else { "false" }),
+        );
+    }
+    res
+}
+
+pub async fn list_links_handler(
+    State(state): State<AppState>,
+    actor: Actor,
+    Query(query): Query<LinkListQuery>,
+) -> Result<Json<Vec<ShortenResponse>>, AppError> {
+    require_api_key(&actor)?;
+
+    if let Err(e) = query.validate() {
+        return Err(AppError::ValidationError(e.to_string()));
+    }
+
+    let links = list_links(&state.db, &actor.name, &query).await?;
+    let base_url = &state.config.public_base_url;
+
+    Ok(Json(links.into_iter().map(|link| ShortenResponse::from_link(link, base_url)).collect()))
+}
+
+pub async fn get_link_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(slug): axum::extract::Path<String>,
+    actor: Actor,
+) -> Result<Json<ShortenResponse>, AppError> {
+    require_api_key(&actor)?;
+
+    let link = get_owned_link(&state.db, &slug, &actor.name).await?;
+
+    Ok(Json(ShortenResponse::from_link(link, &state.config.public_base_url)))
+}
+
+pub async fn update_link_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(slug): axum::extract::Path<String>,
+    actor: Actor,
+    Json(payload): Json<UpdateLinkRequest>,
+) -> Result<Json<ShortenResponse>, AppError> {
+    require_api_key(&actor)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(AppError::ValidationError(e.to_string()));
+    }
+
+    let link = update_link(&state.db, &slug, payload, &actor).await?;
+
+    Ok(Json(ShortenResponse::from_link(link, &state.config.public_base_url)))
+}
+
+pub async fn history_handler(
+    State(db): State<sqlx::PgPool>,
+    axum::extract::Path(slug): axum::extract::Path<String>,
+    actor: Actor,
+) -> Result<Json<Vec<LinkRevision>>, AppError> {
+    require_api_key(&actor)?;
+
+    let link = get_owned_link(&db, &slug, &actor.name).await?;
+
+    Ok(Json(list_revisions(&db, &link.slug).await?))
+}
+
+pub async fn rollback_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(slug): axum::extract::Path<String>,
+    actor: Actor,
+    Json(payload): Json<RollbackRequest>,
+) -> Result<Json<ShortenResponse>, AppError> {
+    require_api_key(&actor)?;
 
     if let Err(e) = payload.validate() {
         return Err(AppError::ValidationError(e.to_string()));
     }
 
-    let slug = create_short_link(&db, payload.target_url, payload.custom_slug, payload.expires_in)
-        .await?;
+    let link = rollback_link(&state.db, &slug, payload.revision, &actor).await?;
+
+    Ok(Json(ShortenResponse::from_link(link, &state.config.public_base_url)))
+}
+
+pub async fn create_alias_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(slug): axum::extract::Path<String>,
+    actor: Actor,
+    Json(payload): Json<CreateAliasRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_api_key(&actor)?;
+
+    if let Err(e) = payload.validate() {
+        return Err(AppError::ValidationError(e.to_string()));
+    }
+
+    let alias = create_alias(&state.db, &slug, &payload.alias, &actor).await?;
+    let location = format!("{}/{}", state.config.public_base_url, alias.alias);
+
+    Ok((StatusCode::CREATED, [(header::LOCATION, location)], Json(alias)))
+}
+
+pub async fn list_aliases_handler(
+    State(db): State<sqlx::PgPool>,
+    axum::extract::Path(slug): axum::extract::Path<String>,
+    actor: Actor,
+) -> Result<Json<Vec<LinkAlias>>, AppError> {
+    require_api_key(&actor)?;
+
+    Ok(Json(list_aliases(&db, &slug, &actor.name).await?))
+}
+
+pub async fn delete_alias_handler(
+    State(db): State<sqlx::PgPool>,
+    axum::extract::Path((slug, alias)): axum::extract::Path<(String, String)>,
+    actor: Actor,
+) -> Result<StatusCode, AppError> {
+    require_api_key(&actor)?;
+
+    delete_alias(&db, &slug, &alias, &actor).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn qr_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(slug): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let link = get_link(&state.db, &slug).await?;
+    let short_url = format!("{}/{}", state.config.public_base_url, link.slug);
+
+    let code = QrCode::new(short_url.as_bytes())
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let image = code.render::<svg::Color>().min_dimensions(200, 200).build();
 
-    Ok(Json(ShortenResponse { slug }))
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], image))
 }
 
 pub async fn resolve_handler(
     State(db): State<sqlx::PgPool>,
+    State(config): State<Arc<Config>>,
+    State(mut redis): State<MultiplexedConnection>,
     axum::extract::Path(slug): axum::extract::Path<String>,
-    metadata : ClickEvent
-) -> Result<axum::response::Redirect, AppError> {
-    let target_url = match crate::services::link::resolve_slug(&db, slug).await {
-        Ok(url) => url,
+    mut metadata : ClickEvent
+) -> Result<axum::response::Response, AppError> {
+    let resolved = match crate::services::link::resolve_slug(&db, slug).await {
+        Ok(resolved) => resolved,
         Err(AppError::NotFound(_)) => {
             return Err(AppError::NotFound("Shortlink not found".to_string()));
         }
@@ -38,10 +227,95 @@ pub async fn resolve_handler(
         }
     };
 
+    // Record the click against the primary link, remembering the alias used
+    metadata.slug = resolved.slug;
+    metadata.alias = resolved.alias;
+
+    // Browsers without a visitor cookie get one, so their next click is recognised as returning
+    let issued_cookie = metadata.visitor_cookie.is_none().then(|| {
+        let visitor_id = hex::encode(rand::rng().random::<[u8; 16]>());
+        metadata.visitor_cookie = Some(visitor_id.clone());
+        metadata.visitor_cookie_issued = true;
+        visitor_cookie_header(&visitor_id, config.public_base_url.starts_with("https://"))
+    });
+
+    // Links tracking conversions pass the click id to the destination to report them with
+    let destination = if resolved.track_conversions {
+        let click_id = hex::encode(rand::rng().random::<[u8; 16]>());
+        let destination = destination_with_click_id(&resolved.target_url, &click_id);
+        metadata.click_id = Some(click_id);
+        destination
+    } else {
+        resolved.target_url
+    };
+
+    // Realtime counters are best effort and skip obvious bots, like the default analytics
+    if classify_bot(&metadata).is_none() {
+        if let Err(e) = record_click(&mut redis, &metadata).await {
+            tracing::warn!("Failed to update realtime counters for {}: {:?}", metadata.slug, e);
+        }
+    }
    
-       publish_click_event(metadata).await
+       publish_click_event(&mut redis, metadata).await
         .map_err(|e| AppError::InternalServerError(e.to_string()))?;
           
 
-    Ok(axum::response::Redirect::to(&target_url))
-}
\ No newline at end of file
+    let mut response = axum::response::Redirect::to(&destination).into_response();
+    if let Some(cookie) = issued_cookie.and_then(|cookie| header::HeaderValue::from_str(&cookie).ok()) {
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+
+    Ok(response)
+}
+
+// Target URLs are validated on creation, but one that no longer parses is used as it is
+fn destination_with_click_id(target_url: &str, click_id: &str) -> String {
+    match url::Url::parse(target_url) {
+        Ok(mut url) => {
+            url.query_pairs_mut().append_pair(CLICK_ID_PARAM, click_id);
+            url.to_string()
+        }
+        Err(_) => target_url.to_string(),
+    }
+}
+
+// Links are managed and inspected by the owner of an API key
+fn require_api_key(actor: &Actor) -> Result<(), AppError> {
+    if actor.is_anonymous() {
+        return Err(AppError::Unauthorized("An API key is required to manage links".to_string()));
+    }
+    Ok(())
+}
+
+fn visitor_cookie_header(visitor_id: &str, secure: bool) -> String {
+    let mut cookie = format!(
+        "{}={}; Max-Age={}; Path=/; HttpOnly; SameSite=Lax",
+        VISITOR_COOKIE, visitor_id, VISITOR_COOKIE_MAX_AGE_SECS
+    );
+    if secure {
+        cookie.push_str("; Secure");
+    }
+    cookie
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn click_id_is_appended_to_the_query() {
+        assert_eq!(
+            destination_with_click_id("https://example.com/signup", "abc"),
+            "https://example.com/signup?lp_click_id=abc"
+        );
+        assert_eq!(
+            destination_with_click_id("https://example.com/?ref=ad#form", "abc"),
+            "https://example.com/?ref=ad&lp_click_id=abc#form"
+        );
+    }
+
+    #[test]
+    fn unparseable_targets_are_left_alone() {
+        assert_eq!(destination_with_click_id("not a url", "abc"), "not a url");
+    }
+}
@@ -1,16 +1,80 @@
 mod link;
 mod analytics;
+mod campaign;
+mod conversion;
 
-use axum::{routing::{post, get}, Router};
+use std::sync::Arc;
+
+use axum::{extract::FromRef, routing::{delete, post, get}, Router};
+use redis::aio::MultiplexedConnection;
 use sqlx::PgPool;
 
-use crate::routes::{analytics::analytics_handler, link::{resolve_handler, shorten_handler}};
+use crate::{
+    config::Config,
+    routes::{
+        analytics::{
+            analytics_handler, campaign_analytics_handler, campaign_heatmap_handler, export_handler, export_summary_handler,
+            heatmap_handler, live_analytics_handler, query_handler, realtime_analytics_handler, top_links_handler,
+        },
+        campaign::{create_campaign_handler, get_campaign_handler, list_campaigns_handler},
+        conversion::{convert_pixel_handler, postback_handler},
+        link::{
+            create_alias_handler, delete_alias_handler, get_link_handler, history_handler, list_aliases_handler,
+            list_links_handler, qr_handler, resolve_handler, rollback_handler, shorten_handler, update_link_handler,
+        },
+    },
+};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: PgPool,
+    pub config: Arc<Config>,
+    // Shared by every request; a multiplexed connection is cheap to clone
+    pub redis: MultiplexedConnection,
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<AppState> for MultiplexedConnection {
+    fn from_ref(state: &AppState) -> Self {
+        state.redis.clone()
+    }
+}
 
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
 
-pub fn create_router(db: PgPool) -> Router {
+pub fn create_router(state: AppState) -> Router {
     Router::new()
         .route("/shorten", post(shorten_handler))
+        .route("/convert", get(convert_pixel_handler))
+        .route("/conversions", post(postback_handler))
         .route("/{capture}", get(resolve_handler))
+        .route("/links", get(list_links_handler))
+        .route("/links/{capture}", get(get_link_handler).patch(update_link_handler))
+        .route("/links/{capture}/history", get(history_handler))
+        .route("/links/{capture}/rollback", post(rollback_handler))
+        .route("/links/{capture}/qr", get(qr_handler))
+        .route("/links/{capture}/aliases", post(create_alias_handler).get(list_aliases_handler))
+        .route("/links/{capture}/aliases/{alias}", delete(delete_alias_handler))
+        .route("/analytics/query", post(query_handler))
+        .route("/analytics/top", get(top_links_handler))
         .route("/analytics/{capture}", get(analytics_handler))
-        .with_state(db)
+        .route("/analytics/{capture}/heatmap", get(heatmap_handler))
+        .route("/analytics/{capture}/realtime", get(realtime_analytics_handler))
+        .route("/analytics/{capture}/live", get(live_analytics_handler))
+        .route("/analytics/{capture}/export", get(export_handler))
+        .route("/analytics/{capture}/export/summary", get(export_summary_handler))
+        .route("/campaigns", post(create_campaign_handler).get(list_campaigns_handler))
+        .route("/campaigns/{capture}", get(get_campaign_handler))
+        .route("/campaigns/{capture}/analytics", get(campaign_analytics_handler))
+        .route("/campaigns/{capture}/analytics/heatmap", get(campaign_heatmap_handler))
+        .with_state(state)
 }
@@ -1,26 +1,81 @@
-use crate::{errors::AppError, models::analytics::{AnalyticsRequest, ReferrerData, UserAgentData, ClickDistributionData, AnalyticsData, DateRange}};
+use crate::{errors::AppError, models::analytics::{AnalyticsRequest, ReferrerData, UserAgentData, AliasData, DimensionData, ClickDistributionData, VisitorDistributionData, AnalyticsData, ConversionData, conversion_rate, DateRange, AnalyticsComparison, AnalyticsDeltas, HeatmapData, LinkMetadata, UNIQUE_CLICKS_SCOPE}, services::{link::{get_link, primary_slug}, rollup::{ClickSource, DIMENSION_ALIAS, DIMENSION_BOT_NAME, DIMENSION_BOT_REASON, DIMENSION_BROWSER, DIMENSION_BROWSER_VERSION, DIMENSION_CHANNEL, DIMENSION_CITY, DIMENSION_DEVICE, DIMENSION_OS, DIMENSION_REFERRER, DIMENSION_REFERRER_DOMAIN, DIMENSION_REGION, DIMENSION_TOTAL, DIMENSION_USER_AGENT, DIMENSION_VISITOR}}};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use sqlx::{PgPool, query_as, Transaction, Postgres};
 
 
-async fn build_filter_clause(slug: &str, params: &AnalyticsRequest) -> (String, Vec<String>, Option<DateRange>) {
+// Which clicks an analytics query aggregates over
+pub enum AnalyticsScope {
+    Link(String),
+    Campaign(i32),
+}
+
+const TZ_PARAM: &str = "$2";
+
+// Click timestamp converted to the requested timezone
+fn local_timestamp() -> String {
+    format!("(timestamp AT TIME ZONE {})", TZ_PARAM)
+}
+
+// UTC instant a local date starts at. Comparing the raw timestamp against it, instead of
+// converting every click to a local date, keeps the (slug, timestamp) index usable.
+fn local_day_start(date: &str) -> String {
+    format!("({}::date::timestamp AT TIME ZONE {})", date, TZ_PARAM)
+}
+
+fn local_day_end(date: &str) -> String {
+    format!("(({}::date + 1)::timestamp AT TIME ZONE {})", date, TZ_PARAM)
+}
+
+// Filter for the clicks an analytics response counts; bots and duplicates are excluded unless requested
+pub async fn build_filter_clause(scope: &AnalyticsScope, params: &AnalyticsRequest) -> (String, Vec<String>, Option<DateRange>) {
+    let (mut date_filter, query_params, date_range) = build_click_filter(scope, params).await;
+
+    if !params.include_bots() {
+        date_filter.push_str(" AND NOT is_bot");
+    }
+    if !params.include_duplicates() {
+        date_filter.push_str(" AND NOT is_duplicate");
+    }
+
+    (date_filter, query_params, date_range)
+}
 
-    let mut date_filter = String::from("slug = $1");
-    let mut query_params = vec![slug.to_string()];
-    let mut arg_index = 2;
+// Scope, date and country filter, matching human and bot clicks alike
+async fn build_click_filter(scope: &AnalyticsScope, params: &AnalyticsRequest) -> (String, Vec<String>, Option<DateRange>) {
+
+    let (mut date_filter, mut query_params) = match scope {
+        AnalyticsScope::Link(slug) => (String::from("slug = $1"), vec![slug.clone()]),
+        AnalyticsScope::Campaign(id) => (
+            String::from("slug IN (SELECT slug FROM links WHERE campaign_id = $1::int)"),
+            vec![id.to_string()],
+        ),
+    };
+
+    // The requested timezone is always bound as $2 (see TZ_PARAM)
+    query_params.push(params.timezone().to_string());
+    let mut arg_index = 3;
 
     let date_range = None;
     
 
     if let Some(start_date) = &params.start_date {
-        date_filter.push_str(&format!(" AND timestamp::date >= ${}::date", arg_index));
+        date_filter.push_str(&format!(" AND timestamp >= {}", local_day_start(&format!("${}", arg_index))));
         query_params.push(start_date.clone());
         arg_index += 1;
     }
 
 
     if let Some(end_date) = &params.end_date {
-        date_filter.push_str(&format!(" AND timestamp::date <= ${}::date", arg_index));
+        // Half-open, so the end date's last instant is included without rounding
+        date_filter.push_str(&format!(" AND timestamp < {}", local_day_end(&format!("${}", arg_index))));
         query_params.push(end_date.clone());
+        arg_index += 1;
+    }
+
+    if let Some(country) = &params.country {
+        date_filter.push_str(&format!(" AND country = ${}", arg_index));
+        query_params.push(country.to_uppercase());
     }
     
     (date_filter, query_params, date_range)
@@ -30,9 +85,10 @@ async fn build_filter_clause(slug: &str, params: &AnalyticsRequest) -> (String,
 async fn calculate_date_range(
     tx: &mut Transaction<'_, Postgres>,
     start_date: &str,
-    end_date: &str
+    end_date: &str,
+    timezone: &str,
 ) -> Result<DateRange, AppError> {
-    let days_query = "SELECT DATE_PART('day', $1::date - $2::date) + 1 as days";
+    let days_query = "SELECT ($1::date - $2::date + 1)::bigint as days";
     
     let (days,): (i64,) = query_as(days_query)
         .bind(end_date)
@@ -45,6 +101,7 @@ async fn calculate_date_range(
         start: start_date.to_string(),
         end: end_date.to_string(),
         days,
+        timezone: timezone.to_string(),
     })
 }
 
@@ -93,22 +150,203 @@ where
         .map_err(|e| AppError::DatabaseError(format!("{}: {}", error_context, e)))
 }
 
+// Visitor hashes are salted per UTC day, so a visitor is only recognisable within one day.
+// Uniques are counted per UTC day and summed over the range, for hashed and IP-keyed clicks alike.
+pub const UNIQUE_VISITOR_DAYS: &str =
+    "COUNT(DISTINCT (date_trunc('day', timestamp AT TIME ZONE 'UTC'), COALESCE(visitor_id, ip)))";
+
+// Whether a clicks row received a conversion
+const CONVERTED: &str = "EXISTS (SELECT 1 FROM conversions WHERE conversions.click_id = clicks.click_id)";
+
+// Clicks and conversions per referrer domain and per variant (the alias used, or the slug),
+// most conversions first. Conversions are joined to raw clicks, so both come from one scan.
+async fn conversion_breakdowns(
+    tx: &mut Transaction<'_, Postgres>,
+    date_filter: &str,
+    params: &[&str],
+    limit: i64,
+) -> Result<(Vec<ConversionData>, Vec<ConversionData>), AppError> {
+    let query = format!(
+        "SELECT is_referrer, value, clicks, conversions FROM (
+             SELECT is_referrer, value, clicks, conversions,
+                    ROW_NUMBER() OVER (PARTITION BY is_referrer ORDER BY conversions DESC, clicks DESC) AS rank
+             FROM (
+                 SELECT GROUPING(referrer_domain) = 0 AS is_referrer,
+                        CASE WHEN GROUPING(referrer_domain) = 0 THEN COALESCE(referrer_domain, 'Unknown')
+                             ELSE COALESCE(alias, slug) END AS value,
+                        COUNT(*) AS clicks,
+                        COUNT(*) FILTER (WHERE {}) AS conversions
+                 FROM clicks WHERE {}
+                 GROUP BY GROUPING SETS ((referrer_domain), (COALESCE(alias, slug)))
+             ) AS grouped
+         ) AS ranked
+         WHERE rank <= {}
+         ORDER BY is_referrer DESC, rank",
+        CONVERTED, date_filter, limit
+    );
+
+    let results: Vec<(bool, String, i64, i64)> = execute_multi_query(tx, &query, params, "Conversion Breakdowns").await?;
+
+    let (referrers, variants): (Vec<_>, Vec<_>) = results.into_iter().partition(|(is_referrer, ..)| *is_referrer);
+    let to_data = |rows: Vec<(bool, String, i64, i64)>| {
+        rows.into_iter()
+            .map(|(_, value, clicks, conversions)| ConversionData {
+                value,
+                clicks,
+                conversions,
+                conversion_rate: conversion_rate(conversions, clicks),
+            })
+            .collect()
+    };
+
+    Ok((to_data(referrers), to_data(variants)))
+}
+
+// Top values of a column of a rollup-backed source (see ClickSource::subquery)
+async fn rolled_up_dimension(
+    tx: &mut Transaction<'_, Postgres>,
+    column: &str,
+    source: &str,
+    date_filter: &str,
+    params: &[&str],
+    limit: Option<i64>,
+    error_context: &str,
+) -> Result<Vec<DimensionData>, AppError> {
+    let query = format!(
+        "SELECT COALESCE({}, 'Unknown') AS value, SUM(count)::bigint as count
+         FROM {} WHERE {}
+         GROUP BY 1
+         ORDER BY count DESC
+         {}",
+        column,
+        source,
+        date_filter,
+        limit.map(|limit| format!("LIMIT {}", limit)).unwrap_or_default()
+    );
+
+    let results: Vec<(String, i64)> = execute_multi_query(tx, &query, params, error_context).await?;
+
+    Ok(results
+        .into_iter()
+        .map(|(value, count)| DimensionData { value, count })
+        .collect())
+}
+
 pub async fn get_analytics_data(db: &PgPool, slug: String, params: &AnalyticsRequest) -> Result<AnalyticsData, AppError> {
+    // Aliases share the analytics of their primary link
+    let slug = primary_slug(db, &slug).await?;
+    // Unknown slugs are missing; existing links without clicks get zero-filled analytics
+    let link = get_link(db, &slug).await?;
+    let scope = AnalyticsScope::Link(slug);
+    let mut analytics = get_scoped_analytics(db, &scope, params).await?;
+    analytics.link = Some(LinkMetadata::from_link(link));
+
+    attach_comparison(db, &scope, params, &mut analytics).await?;
+    Ok(analytics)
+}
+
+pub async fn get_campaign_analytics(db: &PgPool, campaign_id: i32, params: &AnalyticsRequest) -> Result<AnalyticsData, AppError> {
+    let scope = AnalyticsScope::Campaign(campaign_id);
+    let mut analytics = get_scoped_analytics(db, &scope, params).await?;
+
+    attach_comparison(db, &scope, params, &mut analytics).await?;
+    Ok(analytics)
+}
+
+pub async fn get_link_heatmap(db: &PgPool, slug: String, params: &AnalyticsRequest) -> Result<HeatmapData, AppError> {
+    let slug = primary_slug(db, &slug).await?;
+    get_link(db, &slug).await?;
+    get_heatmap(db, &AnalyticsScope::Link(slug), params).await
+}
+
+pub async fn get_campaign_heatmap(db: &PgPool, campaign_id: i32, params: &AnalyticsRequest) -> Result<HeatmapData, AppError> {
+    get_heatmap(db, &AnalyticsScope::Campaign(campaign_id), params).await
+}
+
+const WEEKDAYS: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+async fn get_heatmap(db: &PgPool, scope: &AnalyticsScope, params: &AnalyticsRequest) -> Result<HeatmapData, AppError> {
+    let mut tx = db.begin().await
+        .map_err(|e| AppError::DatabaseError(format!("Failed to start transaction: {}", e)))?;
+
+    let (date_filter, params_vec, _) = build_filter_clause(scope, params).await;
+    let params_refs: Vec<&str> = params_vec.iter().map(|s| s.as_str()).collect();
+
+    let date_range = match (&params.start_date, &params.end_date) {
+        (Some(start), Some(end)) => Some(calculate_date_range(&mut tx, start, end, params.timezone()).await?),
+        _ => None,
+    };
+
+    // Hourly rollups line up with local hours whenever the timezone is whole-hour
+    let heatmap_query = format!(
+        "SELECT EXTRACT(ISODOW FROM {local})::int - 1 AS day, EXTRACT(HOUR FROM {local})::int AS hour,
+                SUM(count)::bigint AS count
+         FROM {source} WHERE {filter}
+         GROUP BY 1, 2",
+        local = local_timestamp(),
+        source = ClickSource::for_request(params.timezone(), "hour").subquery(DIMENSION_TOTAL),
+        filter = date_filter
+    );
+
+    let cells: Vec<(i32, i32, i64)> = execute_multi_query(&mut tx, &heatmap_query, &params_refs, "Heatmap").await?;
+
+    tx.commit().await
+        .map_err(|e| AppError::DatabaseError(format!("Failed to commit transaction: {}", e)))?;
+
+    let mut matrix = vec![vec![0; 24]; WEEKDAYS.len()];
+    for (day, hour, count) in cells {
+        matrix[day as usize][hour as usize] = count;
+    }
+
+    Ok(HeatmapData {
+        days: WEEKDAYS.iter().map(|day| day.to_string()).collect(),
+        total_clicks: matrix.iter().flatten().sum(),
+        matrix,
+        date_range,
+        timezone: params.timezone().to_string(),
+    })
+}
+
+// Aggregates the comparison window, if one was requested, and attaches it with the deltas
+async fn attach_comparison(
+    db: &PgPool,
+    scope: &AnalyticsScope,
+    params: &AnalyticsRequest,
+    analytics: &mut AnalyticsData,
+) -> Result<(), AppError> {
+    let (Some(mode), Some(comparison_params)) = (params.compare, params.comparison_request()) else {
+        return Ok(());
+    };
+
+    let previous = get_scoped_analytics(db, scope, &comparison_params).await?;
+    let deltas = AnalyticsDeltas::between(analytics, &previous);
+
+    analytics.comparison = Some(Box::new(AnalyticsComparison { mode, previous, deltas }));
+    Ok(())
+}
+
+async fn get_scoped_analytics(db: &PgPool, scope: &AnalyticsScope, params: &AnalyticsRequest) -> Result<AnalyticsData, AppError> {
 
     let mut tx = db.begin().await
         .map_err(|e| AppError::DatabaseError(format!("Failed to start transaction: {}", e)))?;
 
  
-    let (date_filter, params_vec, mut date_range) = build_filter_clause(&slug, params).await;
+    let (date_filter, params_vec, mut date_range) = build_filter_clause(scope, params).await;
     
 
     let params_refs: Vec<&str> = params_vec.iter().map(|s| s.as_str()).collect();
 
     if let (Some(start), Some(end)) = (&params.start_date, &params.end_date) {
-        date_range = Some(calculate_date_range(&mut tx, start, end).await?);
+        date_range = Some(calculate_date_range(&mut tx, start, end, params.timezone()).await?);
     }
 
-    let total_clicks_query = format!("SELECT COUNT(*) FROM clicks WHERE {}", date_filter);
+    // Counts and breakdowns read the rollups for every click already rolled up
+    let source = ClickSource::for_request(params.timezone(), "day");
+
+    let total_clicks_query = format!(
+        "SELECT COALESCE(SUM(count), 0)::bigint FROM {} WHERE {}",
+        source.subquery(DIMENSION_TOTAL), date_filter
+    );
     let (total_clicks,): (i64,) = execute_count_query(
         &mut tx, 
         &total_clicks_query, 
@@ -116,87 +354,198 @@ pub async fn get_analytics_data(db: &PgPool, slug: String, params: &AnalyticsReq
         "Total Clicks"
     ).await?;
 
-    // Return early if no clicks found
-    if total_clicks == 0 {
-        return Err(AppError::NotFound(format!("No analytics found for slug '{}'", slug)));
-    }
-
-    // Get unique clicks (by IP)
-    let unique_clicks_query = format!("SELECT COUNT(DISTINCT ip) FROM clicks WHERE {}", date_filter);
-    let (unique_clicks,): (i64,) = execute_count_query(
-        &mut tx, 
-        &unique_clicks_query, 
-        &params_refs, 
-        "Unique Clicks"
+    // Unique visitors and conversions need the raw clicks, so they are counted in one scan.
+    // Uniques use the daily visitor hash; clicks stored before hashing fall back to the IP.
+    // Conversion rates are taken over the same raw clicks the conversions are joined to.
+    let (unique_clicks, counted_clicks, conversions): (i64, i64, i64) = execute_count_query(
+        &mut tx,
+        &format!(
+            "SELECT {}, COUNT(*), COUNT(*) FILTER (WHERE {}) FROM clicks WHERE {}",
+            UNIQUE_VISITOR_DAYS, CONVERTED, date_filter
+        ),
+        &params_refs,
+        "Unique Clicks And Conversions"
     ).await?;
 
     // Get top referrers
     let referrer_limit = params.referer_quantity.unwrap_or(10);
-    let top_referrers_query = format!(
-        "SELECT COALESCE(referer, 'Unknown') AS referer, COUNT(*) as count
-         FROM clicks WHERE {}
-         GROUP BY referer
-         ORDER BY count DESC
-         LIMIT {}",
-        date_filter, referrer_limit
-    );
-    
-    let referrer_results: Vec<(String, i64)> = execute_multi_query(
-        &mut tx, 
-        &top_referrers_query, 
-        &params_refs, 
-        "Top Referrers"
-    ).await?;
-    
-    let top_referrers = referrer_results
+    let top_referrers = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_REFERRER), &date_filter, &params_refs, Some(referrer_limit), "Top Referrers")
+        .await?
         .into_iter()
-        .map(|(referer, count)| ReferrerData { referer, count })
+        .map(|row| ReferrerData { referer: row.value, count: row.count })
         .collect();
 
+    // Get normalized referrer breakdowns
+    let referrer_domains = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_REFERRER_DOMAIN), &date_filter, &params_refs, Some(referrer_limit), "Referrer Domains").await?;
+    let channels = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_CHANNEL), &date_filter, &params_refs, Some(referrer_limit), "Channels").await?;
+
     // Get top user agents
     let user_agent_limit = params.user_agent_quantity.unwrap_or(10);
-    let top_user_agents_query = format!(
-        "SELECT COALESCE(user_agent, 'Unknown') AS user_agent, COUNT(*) as count
-         FROM clicks WHERE {}
-         GROUP BY user_agent
-         ORDER BY count DESC
-         LIMIT {}",
-        date_filter, user_agent_limit
-    );
-    
-    let user_agent_results: Vec<(String, i64)> = execute_multi_query(
-        &mut tx, 
-        &top_user_agents_query, 
-        &params_refs, 
-        "Top User Agents"
-    ).await?;
-    
-    let top_user_agents = user_agent_results
+    let top_user_agents = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_USER_AGENT), &date_filter, &params_refs, Some(user_agent_limit), "Top User Agents")
+        .await?
         .into_iter()
-        .map(|(user_agent, count)| UserAgentData { user_agent, count })
+        .map(|row| UserAgentData { user_agent: row.value, count: row.count })
         .collect();
 
-    // Get click distribution by date
-    let click_distribution_limit = params.click_distribution_quantity.unwrap_or(30);
+    // Get parsed user agent breakdowns
+    let browsers = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_BROWSER), &date_filter, &params_refs, Some(user_agent_limit), "Browsers").await?;
+    let browser_versions = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_BROWSER_VERSION), &date_filter, &params_refs, Some(user_agent_limit), "Browser Versions").await?;
+    let operating_systems = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_OS), &date_filter, &params_refs, Some(user_agent_limit), "Operating Systems").await?;
+    let device_types = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_DEVICE), &date_filter, &params_refs, Some(user_agent_limit), "Device Types").await?;
+
+    // Get geo breakdowns; regions and cities are qualified by country
+    let geo_limit = params.referer_quantity.unwrap_or(10);
+    let countries = rolled_up_dimension(&mut tx, "country", &source.subquery(DIMENSION_TOTAL), &date_filter, &params_refs, Some(geo_limit), "Countries").await?;
+    let regions = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_REGION), &date_filter, &params_refs, Some(geo_limit), "Regions").await?;
+    let cities = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_CITY), &date_filter, &params_refs, Some(geo_limit), "Cities").await?;
+
+    // Get clicks per alias used to reach the link
+    let alias_breakdown = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_ALIAS), &date_filter, &params_refs, None, "Alias Breakdown")
+        .await?
+        .into_iter()
+        .map(|row| AliasData { alias: row.value, count: row.count })
+        .collect();
+
+    // Get bot clicks by detection reason and by bot, whether or not bots are counted above
+    let (click_filter, _, _) = build_click_filter(scope, params).await;
+    let bot_filter = format!("{} AND is_bot", click_filter);
+    let (bot_clicks,): (i64,) = execute_count_query(
+        &mut tx,
+        &format!("SELECT COALESCE(SUM(count), 0)::bigint FROM {} WHERE {}", source.subquery(DIMENSION_TOTAL), bot_filter),
+        &params_refs,
+        "Bot Clicks"
+    ).await?;
+    // Repeat clicks, under the same bot filter as the counts above
+    let mut duplicate_filter = format!("{} AND is_duplicate", click_filter);
+    if !params.include_bots() {
+        duplicate_filter.push_str(" AND NOT is_bot");
+    }
+    let (duplicate_clicks,): (i64,) = execute_count_query(
+        &mut tx,
+        &format!("SELECT COALESCE(SUM(count), 0)::bigint FROM {} WHERE {}", source.subquery(DIMENSION_TOTAL), duplicate_filter),
+        &params_refs,
+        "Duplicate Clicks"
+    ).await?;
+
+    let (new_visitor_clicks, returning_visitor_clicks): (i64, i64) = execute_count_query(
+        &mut tx,
+        &format!(
+            "SELECT COALESCE(SUM(count) FILTER (WHERE value = 'new'), 0)::bigint,
+                    COALESCE(SUM(count) FILTER (WHERE value = 'returning'), 0)::bigint
+             FROM {} WHERE {}",
+            source.subquery(DIMENSION_VISITOR), date_filter
+        ),
+        &params_refs,
+        "New And Returning Visitors"
+    ).await?;
+
+    let (referrer_conversions, variant_conversions) = conversion_breakdowns(&mut tx, &date_filter, &params_refs, referrer_limit).await?;
+
+    let bot_reasons = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_BOT_REASON), &bot_filter, &params_refs, Some(user_agent_limit), "Bot Reasons").await?;
+    let bots = rolled_up_dimension(&mut tx, "value", &source.subquery(DIMENSION_BOT_NAME), &bot_filter, &params_refs, Some(user_agent_limit), "Bots").await?;
+
+    // Get click distribution per bucket, zero-filled across the requested range
+    let granularity = params.granularity();
+    let unit = granularity.unit();
+    let mut distribution_params = params_refs.clone();
+
+    let start_param = params.start_date.as_deref().map(|start| {
+        distribution_params.push(start);
+        format!("${}", distribution_params.len())
+    });
+    let end_param = params.end_date.as_deref().map(|end| {
+        distribution_params.push(end);
+        format!("${}", distribution_params.len())
+    });
+
+    let last_bucket = match &end_param {
+        Some(end) => format!("date_trunc('{}', ({}::date + 1)::timestamp - interval '1 microsecond')", unit, end),
+        None => format!("date_trunc('{}', NOW() AT TIME ZONE {})", unit, TZ_PARAM),
+    };
+    let first_bucket = match &start_param {
+        Some(start) => format!("date_trunc('{}', {}::date::timestamp)", unit, start),
+        None => {
+            let buckets = params.click_distribution_quantity.unwrap_or(granularity.default_buckets());
+            format!("{} - interval '1 {}' * {}", last_bucket, unit, buckets - 1)
+        }
+    };
+
     let click_distribution_query = format!(
-        "SELECT to_char(timestamp, 'YYYY-MM-DD') AS date, COUNT(*) as count
-         FROM clicks WHERE {}
-         GROUP BY date
-         ORDER BY date
-         LIMIT {}",
-        date_filter, click_distribution_limit
+        "WITH buckets AS (
+             SELECT generate_series({first}, {last}, interval '1 {unit}') AS bucket
+         ),
+         counts AS (
+             SELECT date_trunc('{unit}', {local}) AS bucket, SUM(count) AS count
+             FROM {source} WHERE {filter}
+             GROUP BY 1
+         )
+         SELECT buckets.bucket AT TIME ZONE {tz} AS bucket, COALESCE(counts.count, 0)::bigint AS count
+         FROM buckets LEFT JOIN counts ON counts.bucket = buckets.bucket
+         ORDER BY buckets.bucket",
+        first = first_bucket,
+        last = last_bucket,
+        unit = unit,
+        local = local_timestamp(),
+        source = ClickSource::for_request(params.timezone(), unit).subquery(DIMENSION_TOTAL),
+        filter = date_filter,
+        tz = TZ_PARAM
     );
     
-    let distribution_results: Vec<(String, i64)> = execute_multi_query(
+    let distribution_results: Vec<(DateTime<Utc>, i64)> = execute_multi_query(
         &mut tx, 
         &click_distribution_query, 
-        &params_refs, 
+        &distribution_params, 
         "Click Distribution"
     ).await?;
-    
+
+    // Same buckets, split by new and returning visitors
+    let visitor_distribution_query = format!(
+        "WITH buckets AS (
+             SELECT generate_series({first}, {last}, interval '1 {unit}') AS bucket
+         ),
+         counts AS (
+             SELECT date_trunc('{unit}', {local}) AS bucket,
+                    SUM(count) FILTER (WHERE value = 'new') AS new,
+                    SUM(count) FILTER (WHERE value = 'returning') AS returning
+             FROM {source} WHERE {filter}
+             GROUP BY 1
+         )
+         SELECT buckets.bucket AT TIME ZONE {tz} AS bucket,
+                COALESCE(counts.new, 0)::bigint AS new,
+                COALESCE(counts.returning, 0)::bigint AS returning
+         FROM buckets LEFT JOIN counts ON counts.bucket = buckets.bucket
+         ORDER BY buckets.bucket",
+        first = first_bucket,
+        last = last_bucket,
+        unit = unit,
+        local = local_timestamp(),
+        source = ClickSource::for_request(params.timezone(), unit).subquery(DIMENSION_VISITOR),
+        filter = date_filter,
+        tz = TZ_PARAM
+    );
+
+    let visitor_results: Vec<(DateTime<Utc>, i64, i64)> = execute_multi_query(
+        &mut tx,
+        &visitor_distribution_query,
+        &distribution_params,
+        "Visitor Distribution"
+    ).await?;
+
+    let tz: Tz = params.timezone().parse().unwrap_or(Tz::UTC);
     let click_distribution = distribution_results
         .into_iter()
-        .map(|(date, count)| ClickDistributionData { date, count })
+        .map(|(bucket, count)| ClickDistributionData {
+            timestamp: bucket.with_timezone(&tz).to_rfc3339(),
+            count,
+        })
+        .collect();
+    let visitor_distribution = visitor_results
+        .into_iter()
+        .map(|(bucket, new, returning)| VisitorDistributionData {
+            timestamp: bucket.with_timezone(&tz).to_rfc3339(),
+            new,
+            returning,
+        })
         .collect();
 
     // Commit the transaction
@@ -205,11 +554,36 @@ pub async fn get_analytics_data(db: &PgPool, slug: String, params: &AnalyticsReq
 
     // Return the complete analytics data
     Ok(AnalyticsData {
+        link: None,
         total_clicks,
         unique_clicks,
+        unique_clicks_scope: UNIQUE_CLICKS_SCOPE.to_string(),
+        bot_clicks,
+        duplicate_clicks,
+        new_visitor_clicks,
+        returning_visitor_clicks,
+        conversions,
+        conversion_rate: conversion_rate(conversions, counted_clicks),
         top_referrers,
+        referrer_domains,
+        channels,
         top_user_agents,
+        browsers,
+        browser_versions,
+        operating_systems,
+        device_types,
+        countries,
+        regions,
+        cities,
+        alias_breakdown,
+        bot_reasons,
+        bots,
+        referrer_conversions,
+        variant_conversions,
+        granularity,
         click_distribution,
+        visitor_distribution,
         date_range,
+        comparison: None,
     })
 }
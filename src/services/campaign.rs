@@ -0,0 +1,58 @@
+use sqlx::{Error, PgPool};
+
+use crate::{errors::AppError, models::campaign::Campaign};
+
+pub async fn create_campaign(
+    db: &PgPool,
+    name: String,
+    description: Option<String>,
+    owner: &str,
+) -> Result<Campaign, AppError> {
+    let res = sqlx::query_as!(
+        Campaign,
+        "INSERT INTO campaigns (name, description, owner) VALUES ($1, $2, $3)
+         RETURNING id, name, description, created_at",
+        name.trim(),
+        description,
+        owner
+    )
+    .fetch_one(db)
+    .await;
+
+    match res {
+        Ok(campaign) => Ok(campaign),
+        Err(e) => {
+            if let Error::Database(db_err) = &e {
+                if db_err.constraint() == Some("campaigns_owner_name_key") {
+                    return Err(AppError::ValidationError("Campaign name already exists".to_string()));
+                }
+            }
+            Err(AppError::DatabaseError(e.to_string()))
+        }
+    }
+}
+
+// Campaigns of other owners are reported as missing
+pub async fn get_campaign(db: &PgPool, id: i32, owner: &str) -> Result<Campaign, AppError> {
+    sqlx::query_as!(
+        Campaign,
+        "SELECT id, name, description, created_at FROM campaigns WHERE id = $1 AND owner = $2",
+        id,
+        owner
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Campaign {} not found", id)))
+}
+
+pub async fn list_campaigns(db: &PgPool, owner: &str) -> Result<Vec<Campaign>, AppError> {
+    let campaigns = sqlx::query_as!(
+        Campaign,
+        "SELECT id, name, description, created_at FROM campaigns WHERE owner = $1 ORDER BY created_at DESC",
+        owner
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(campaigns)
+}
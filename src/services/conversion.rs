@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::{errors::AppError, models::conversion::{Conversion, ConversionSource}};
+
+// How long a pixel conversion waits for its click to be stored before it is dropped
+const PENDING_CONVERSION_WINDOW: Duration = Duration::from_secs(24 * 3600);
+const PENDING_CLEANUP_INTERVAL: Duration = Duration::from_secs(600);
+
+// Records a conversion reported by the visitor's browser. The pixel is unauthenticated and the
+// click may still be queued in the stream, so conversions for click ids that are not stored yet
+// are kept as pending until `run_pending_cleanup` confirms or drops them.
+pub async fn record_pixel_conversion(db: &PgPool, click_id: &str, goal: &str) -> Result<(Conversion, bool), AppError> {
+    let pending_window = chrono::Duration::from_std(PENDING_CONVERSION_WINDOW).unwrap_or(chrono::Duration::MAX);
+
+    let inserted = sqlx::query_as!(
+        Conversion,
+        "INSERT INTO conversions (click_id, goal, source, pending_until)
+         SELECT $1, $2, $3, CASE WHEN EXISTS (SELECT 1 FROM clicks WHERE click_id = $1) THEN NULL ELSE $4::timestamptz END
+         ON CONFLICT (click_id, goal) DO NOTHING
+         RETURNING id, click_id, goal, source, created_at",
+        click_id.to_lowercase(),
+        goal,
+        ConversionSource::Pixel.as_str(),
+        chrono::Utc::now() + pending_window
+    )
+    .fetch_optional(db)
+    .await?;
+
+    match inserted {
+        Some(conversion) => Ok((conversion, true)),
+        None => Ok((existing_conversion(db, click_id, goal).await?, false)),
+    }
+}
+
+// Records a conversion reported by the advertiser's backend, for a stored click on one of the
+// caller's links. A repeated postback returns the first conversion.
+pub async fn record_postback_conversion(
+    db: &PgPool,
+    click_id: &str,
+    goal: &str,
+    owner: &str,
+) -> Result<(Conversion, bool), AppError> {
+    let owned = sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+               SELECT 1 FROM clicks JOIN links ON links.slug = clicks.slug
+               WHERE clicks.click_id = $1 AND links.owner = $2
+           ) AS "owned!""#,
+        click_id.to_lowercase(),
+        owner
+    )
+    .fetch_one(db)
+    .await?;
+
+    if !owned {
+        return Err(AppError::NotFound(format!("Click '{}' not found", click_id)));
+    }
+
+    let inserted = sqlx::query_as!(
+        Conversion,
+        "INSERT INTO conversions (click_id, goal, source) VALUES ($1, $2, $3)
+         ON CONFLICT (click_id, goal) DO NOTHING
+         RETURNING id, click_id, goal, source, created_at",
+        click_id.to_lowercase(),
+        goal,
+        ConversionSource::Postback.as_str()
+    )
+    .fetch_optional(db)
+    .await?;
+
+    if let Some(conversion) = inserted {
+        return Ok((conversion, true));
+    }
+
+    // A pixel may have reported the conversion first; its click is known now, so it is confirmed
+    let existing = sqlx::query_as!(
+        Conversion,
+        "UPDATE conversions SET pending_until = NULL WHERE click_id = $1 AND goal = $2
+         RETURNING id, click_id, goal, source, created_at",
+        click_id.to_lowercase(),
+        goal
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok((existing, false))
+}
+
+// Confirms pending conversions whose click has been stored and drops those whose wait ran out
+pub async fn run_pending_cleanup(db: PgPool) {
+    let mut interval = tokio::time::interval(PENDING_CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = cleanup_pending(&db).await {
+            tracing::error!("Failed to clean up pending conversions: {:?}", e);
+        }
+    }
+}
+
+async fn cleanup_pending(db: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE conversions SET pending_until = NULL
+         WHERE pending_until IS NOT NULL
+           AND EXISTS (SELECT 1 FROM clicks WHERE clicks.click_id = conversions.click_id)"
+    )
+    .execute(db)
+    .await?;
+
+    let dropped = sqlx::query!("DELETE FROM conversions WHERE pending_until <= NOW()")
+        .execute(db)
+        .await?;
+    if dropped.rows_affected() > 0 {
+        tracing::info!("Dropped {} pixel conversions without a click", dropped.rows_affected());
+    }
+
+    Ok(())
+}
+
+async fn existing_conversion(db: &PgPool, click_id: &str, goal: &str) -> Result<Conversion, AppError> {
+    let conversion = sqlx::query_as!(
+        Conversion,
+        "SELECT id, click_id, goal, source, created_at FROM conversions WHERE click_id = $1 AND goal = $2",
+        click_id.to_lowercase(),
+        goal
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(conversion)
+}
@@ -0,0 +1,297 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use axum::body::Bytes;
+use futures::{Stream, StreamExt};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+
+use crate::{
+    errors::AppError,
+    models::{analytics::{AnalyticsData, AnalyticsRequest, ExportFormat}, click::ClickRecord},
+    services::analytics::{build_filter_clause, AnalyticsScope},
+};
+
+// Rows encoded per chunk; for Parquet this is also the row group size
+const EXPORT_BATCH_SIZE: usize = 5_000;
+// Encoded chunks buffered ahead of a slow client before the database read pauses
+const EXPORT_CHANNEL_CAPACITY: usize = 4;
+
+const EXPORT_COLUMNS: &str = "id, slug, alias, timestamp, ip, visitor_id, user_agent, referer, referrer_domain, channel,
+     browser_family, browser_version, os_family, device_type, country, region, city, asn, asn_org,
+     is_bot, bot_reason, bot_name, is_duplicate, is_returning, click_id";
+
+// Streams the matching clicks of a link, encoded in chunks so the result is never held in memory
+pub async fn export_clicks(
+    db: PgPool,
+    slug: String,
+    params: AnalyticsRequest,
+    format: ExportFormat,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+    let (sender, receiver) = mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+    let (filter, query_params, _) = build_filter_clause(&AnalyticsScope::Link(slug.clone()), &params).await;
+
+    tokio::spawn(async move {
+        if let Err(e) = stream_clicks(&db, &filter, &query_params, format, &sender).await {
+            tracing::error!("Click export for {} failed: {}", slug, e);
+            // Ends the response with an error so clients do not take a truncated file as complete
+            let _ = sender.send(Err(std::io::Error::other(e.to_string()))).await;
+        }
+    });
+
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    })
+}
+
+async fn stream_clicks(
+    db: &PgPool,
+    filter: &str,
+    query_params: &[String],
+    format: ExportFormat,
+    sender: &mpsc::Sender<Result<Bytes, std::io::Error>>,
+) -> Result<(), AppError> {
+    let query = format!(
+        "SELECT {} FROM clicks WHERE {} ORDER BY timestamp, id",
+        EXPORT_COLUMNS, filter
+    );
+
+    let mut query_builder = sqlx::query_as::<_, ClickRecord>(&query);
+    for param in query_params {
+        query_builder = query_builder.bind(param);
+    }
+
+    let mut rows = query_builder.fetch(db);
+    let mut encoder = ExportEncoder::new(format)?;
+    let mut batch = Vec::with_capacity(EXPORT_BATCH_SIZE);
+
+    while let Some(row) = rows.next().await {
+        batch.push(row.map_err(|e| AppError::DatabaseError(format!("Click export: {}", e)))?);
+
+        if batch.len() == EXPORT_BATCH_SIZE {
+            send_chunk(sender, encoder.encode(&batch)?).await?;
+            batch.clear();
+        }
+    }
+
+    if !batch.is_empty() {
+        send_chunk(sender, encoder.encode(&batch)?).await?;
+    }
+    send_chunk(sender, encoder.finish()?).await
+}
+
+async fn send_chunk(
+    sender: &mpsc::Sender<Result<Bytes, std::io::Error>>,
+    chunk: Vec<u8>,
+) -> Result<(), AppError> {
+    if chunk.is_empty() {
+        return Ok(());
+    }
+
+    sender.send(Ok(Bytes::from(chunk))).await
+        .map_err(|_| AppError::InternalServerError("Client disconnected".to_string()))
+}
+
+enum ExportEncoder {
+    // Whether the header row has been sent yet
+    Csv { header_sent: bool },
+    Jsonl,
+    Parquet(Box<ArrowWriter<Vec<u8>>>),
+}
+
+impl ExportEncoder {
+    fn new(format: ExportFormat) -> Result<Self, AppError> {
+        Ok(match format {
+            ExportFormat::Csv => ExportEncoder::Csv { header_sent: false },
+            ExportFormat::Jsonl => ExportEncoder::Jsonl,
+            ExportFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(Vec::new(), click_schema(), Some(properties))
+                    .map_err(|e| AppError::InternalServerError(format!("Parquet writer: {}", e)))?;
+                ExportEncoder::Parquet(Box::new(writer))
+            }
+        })
+    }
+
+    // Encodes one batch of rows and returns the bytes ready to send
+    fn encode(&mut self, rows: &[ClickRecord]) -> Result<Vec<u8>, AppError> {
+        match self {
+            ExportEncoder::Csv { header_sent } => {
+                let chunk = csv_chunk(rows, !*header_sent)?;
+                *header_sent = true;
+                Ok(chunk)
+            }
+            ExportEncoder::Jsonl => {
+                let mut chunk = Vec::new();
+                for row in rows {
+                    serde_json::to_writer(&mut chunk, row)
+                        .map_err(|e| AppError::InternalServerError(format!("JSON Lines export: {}", e)))?;
+                    chunk.push(b'\n');
+                }
+                Ok(chunk)
+            }
+            ExportEncoder::Parquet(writer) => {
+                writer.write(&click_batch(rows)?)
+                    .and_then(|_| writer.flush())
+                    .map_err(|e| AppError::InternalServerError(format!("Parquet export: {}", e)))?;
+                // Flushed row groups are complete, so they can be sent before the footer is written
+                Ok(std::mem::take(writer.inner_mut()))
+            }
+        }
+    }
+
+    // Whatever has to follow the last batch, like the Parquet footer
+    fn finish(self) -> Result<Vec<u8>, AppError> {
+        match self {
+            // An export without rows is still a valid CSV file with its header
+            ExportEncoder::Csv { header_sent: false } => csv_chunk(&[], true),
+            ExportEncoder::Csv { header_sent: true } => Ok(Vec::new()),
+            ExportEncoder::Jsonl => Ok(Vec::new()),
+            ExportEncoder::Parquet(writer) => writer.into_inner()
+                .map_err(|e| AppError::InternalServerError(format!("Parquet export: {}", e))),
+        }
+    }
+}
+
+fn csv_chunk(rows: &[ClickRecord], with_header: bool) -> Result<Vec<u8>, AppError> {
+    let csv_error = |e: csv::Error| AppError::InternalServerError(format!("CSV export: {}", e));
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+
+    if with_header {
+        writer.write_record(EXPORT_COLUMNS.split(',').map(str::trim)).map_err(csv_error)?;
+    }
+    for row in rows {
+        writer.serialize(row).map_err(csv_error)?;
+    }
+
+    writer.into_inner()
+        .map_err(|e| AppError::InternalServerError(format!("CSV export: {}", e)))
+}
+
+fn click_schema() -> Arc<Schema> {
+    let text = |name: &str| Field::new(name, DataType::Utf8, true);
+
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("slug", DataType::Utf8, false),
+        text("alias"),
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), false),
+        text("ip"),
+        text("visitor_id"),
+        text("user_agent"),
+        text("referer"),
+        text("referrer_domain"),
+        text("channel"),
+        text("browser_family"),
+        text("browser_version"),
+        text("os_family"),
+        text("device_type"),
+        text("country"),
+        text("region"),
+        text("city"),
+        Field::new("asn", DataType::Int64, true),
+        text("asn_org"),
+        Field::new("is_bot", DataType::Boolean, false),
+        text("bot_reason"),
+        text("bot_name"),
+        Field::new("is_duplicate", DataType::Boolean, false),
+        Field::new("is_returning", DataType::Boolean, true),
+        text("click_id"),
+    ]))
+}
+
+fn click_batch(rows: &[ClickRecord]) -> Result<RecordBatch, AppError> {
+    let text = |value: fn(&ClickRecord) -> Option<&str>| -> ArrayRef {
+        Arc::new(rows.iter().map(value).collect::<StringArray>())
+    };
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(rows.iter().map(|r| r.id).collect::<Int64Array>()),
+        Arc::new(rows.iter().map(|r| Some(r.slug.as_str())).collect::<StringArray>()),
+        text(|r| r.alias.as_deref()),
+        Arc::new(
+            rows.iter()
+                .map(|r| Some(r.timestamp.timestamp_micros()))
+                .collect::<TimestampMicrosecondArray>()
+                .with_timezone("UTC"),
+        ),
+        text(|r| r.ip.as_deref()),
+        text(|r| r.visitor_id.as_deref()),
+        text(|r| r.user_agent.as_deref()),
+        text(|r| r.referer.as_deref()),
+        text(|r| r.referrer_domain.as_deref()),
+        text(|r| r.channel.as_deref()),
+        text(|r| r.browser_family.as_deref()),
+        text(|r| r.browser_version.as_deref()),
+        text(|r| r.os_family.as_deref()),
+        text(|r| r.device_type.as_deref()),
+        text(|r| r.country.as_deref()),
+        text(|r| r.region.as_deref()),
+        text(|r| r.city.as_deref()),
+        Arc::new(rows.iter().map(|r| r.asn).collect::<Int64Array>()),
+        text(|r| r.asn_org.as_deref()),
+        Arc::new(rows.iter().map(|r| Some(r.is_bot)).collect::<BooleanArray>()),
+        text(|r| r.bot_reason.as_deref()),
+        text(|r| r.bot_name.as_deref()),
+        Arc::new(rows.iter().map(|r| Some(r.is_duplicate)).collect::<BooleanArray>()),
+        Arc::new(rows.iter().map(|r| r.is_returning).collect::<BooleanArray>()),
+        text(|r| r.click_id.as_deref()),
+    ];
+
+    RecordBatch::try_new(click_schema(), columns)
+        .map_err(|e| AppError::InternalServerError(format!("Parquet export: {}", e)))
+}
+
+// Flattens the aggregated analytics into `section,value,count` rows
+pub fn analytics_csv(data: &AnalyticsData) -> Result<Vec<u8>, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut rows: Vec<(&str, String, i64)> = vec![
+        ("total_clicks", String::new(), data.total_clicks),
+        ("unique_clicks", String::new(), data.unique_clicks),
+        ("bot_clicks", String::new(), data.bot_clicks),
+        ("duplicate_clicks", String::new(), data.duplicate_clicks),
+        ("new_visitor_clicks", String::new(), data.new_visitor_clicks),
+        ("returning_visitor_clicks", String::new(), data.returning_visitor_clicks),
+        ("conversions", String::new(), data.conversions),
+    ];
+
+    rows.extend(data.top_referrers.iter().map(|r| ("top_referrers", r.referer.clone(), r.count)));
+    rows.extend(data.top_user_agents.iter().map(|u| ("top_user_agents", u.user_agent.clone(), u.count)));
+
+    let dimensions = [
+        ("referrer_domains", &data.referrer_domains),
+        ("channels", &data.channels),
+        ("browsers", &data.browsers),
+        ("browser_versions", &data.browser_versions),
+        ("operating_systems", &data.operating_systems),
+        ("device_types", &data.device_types),
+        ("countries", &data.countries),
+        ("regions", &data.regions),
+        ("cities", &data.cities),
+        ("bot_reasons", &data.bot_reasons),
+        ("bots", &data.bots),
+    ];
+    for (section, values) in dimensions {
+        rows.extend(values.iter().map(|d| (section, d.value.clone(), d.count)));
+    }
+
+    rows.extend(data.referrer_conversions.iter().map(|c| ("referrer_conversions", c.value.clone(), c.conversions)));
+    rows.extend(data.variant_conversions.iter().map(|c| ("variant_conversions", c.value.clone(), c.conversions)));
+    rows.extend(data.alias_breakdown.iter().map(|a| ("alias_breakdown", a.alias.clone(), a.count)));
+    rows.extend(data.click_distribution.iter().map(|c| ("click_distribution", c.timestamp.clone(), c.count)));
+    rows.extend(data.visitor_distribution.iter().map(|v| ("new_visitor_distribution", v.timestamp.clone(), v.new)));
+    rows.extend(data.visitor_distribution.iter().map(|v| ("returning_visitor_distribution", v.timestamp.clone(), v.returning)));
+
+    let csv_error = |e: csv::Error| AppError::InternalServerError(format!("CSV export: {}", e));
+    writer.write_record(["section", "value", "count"]).map_err(csv_error)?;
+    for (section, value, count) in rows {
+        writer.write_record([section, value.as_str(), count.to_string().as_str()]).map_err(csv_error)?;
+    }
+
+    writer.into_inner()
+        .map_err(|e| AppError::InternalServerError(format!("CSV export: {}", e)))
+}
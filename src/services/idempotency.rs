@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{types::Json, PgPool, Postgres, Transaction};
+
+use crate::{errors::AppError, models::link::{ShortenRequest, ShortenResponse}};
+
+pub const MAX_KEY_LENGTH: usize = 255;
+const KEY_CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+// Forgets keys that fell out of the retention window
+pub async fn run_key_cleanup(db: PgPool, ttl: Duration) {
+    let mut interval = tokio::time::interval(KEY_CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let res = sqlx::query!("DELETE FROM idempotency_keys WHERE created_at <= $1", expiry_cutoff(ttl))
+            .execute(&db)
+            .await;
+        match res {
+            Ok(res) if res.rows_affected() > 0 => tracing::info!("Removed {} expired idempotency keys", res.rows_affected()),
+            Ok(_) => {}
+            Err(e) => tracing::error!("Failed to remove expired idempotency keys: {:?}", e),
+        }
+    }
+}
+
+fn expiry_cutoff(ttl: Duration) -> DateTime<Utc> {
+    Utc::now() - chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX)
+}
+
+// Fingerprint of the caller and request body, used to detect a key being reused for a different request
+pub fn request_fingerprint(owner: &str, payload: &ShortenRequest) -> Result<String, AppError> {
+    let body = serde_json::to_vec(payload)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(owner.as_bytes());
+    hasher.update(b"|");
+    hasher.update(&body);
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Claims the key for this request inside the transaction that creates the link. A retry that
+// arrives while the first request is still in flight waits on the claimed row, then gets the
+// stored response; `None` means the key is ours and the caller should create the link.
+pub async fn reserve_key(
+    tx: &mut Transaction<'_, Postgres>,
+    owner: &str,
+    key: &str,
+    fingerprint: &str,
+    ttl: Duration,
+) -> Result<Option<ShortenResponse>, AppError> {
+    // A stale copy of this key is replaced; other expired keys are left to `run_key_cleanup`
+    sqlx::query!(
+        "DELETE FROM idempotency_keys WHERE owner = $1 AND key = $2 AND created_at <= $3",
+        owner,
+        key,
+        expiry_cutoff(ttl)
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    let reserved = sqlx::query_scalar!(
+        "INSERT INTO idempotency_keys (owner, key, fingerprint) VALUES ($1, $2, $3)
+         ON CONFLICT (owner, key) DO NOTHING
+         RETURNING key",
+        owner,
+        key,
+        fingerprint
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    if reserved.is_some() {
+        return Ok(None);
+    }
+
+    let stored = sqlx::query!(
+        r#"SELECT fingerprint, response AS "response: Json<ShortenResponse>"
+           FROM idempotency_keys WHERE owner = $1 AND key = $2"#,
+        owner,
+        key
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    replay_stored(stored.map(|row| (row.fingerprint, row.response.map(|response| response.0))), fingerprint).map(Some)
+}
+
+// The response to replay for a key that is already claimed, given its stored fingerprint and response
+fn replay_stored(
+    stored: Option<(String, Option<ShortenResponse>)>,
+    fingerprint: &str,
+) -> Result<ShortenResponse, AppError> {
+    match stored {
+        Some((stored_fingerprint, _)) if stored_fingerprint != fingerprint => Err(AppError::UnprocessableEntity(
+            "Idempotency-Key was already used with a different request body".to_string(),
+        )),
+        Some((_, Some(response))) => Ok(response),
+        Some((_, None)) => Err(AppError::UnprocessableEntity(
+            "A request with this Idempotency-Key is still being processed".to_string(),
+        )),
+        // The other request expired or rolled back between the insert and the lookup
+        None => Err(AppError::UnprocessableEntity(
+            "A request with this Idempotency-Key did not complete, retry it".to_string(),
+        )),
+    }
+}
+
+// Completes a reservation made by `reserve_key` in the same transaction
+pub async fn store_response(
+    tx: &mut Transaction<'_, Postgres>,
+    owner: &str,
+    key: &str,
+    response: &ShortenResponse,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE idempotency_keys SET response = $3 WHERE owner = $1 AND key = $2",
+        owner,
+        key,
+        Json(response) as _
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::link::LinkResources;
+
+    fn request(target_url: &str) -> ShortenRequest {
+        ShortenRequest {
+            target_url: target_url.to_string(),
+            custom_slug: None,
+            expires_in: None,
+            tags: None,
+            campaign_id: None,
+            track_conversions: None,
+        }
+    }
+
+    fn response() -> ShortenResponse {
+        ShortenResponse {
+            slug: "abc123".to_string(),
+            short_url: "http://sho.rt/abc123".to_string(),
+            target_url: "https://example.com/".to_string(),
+            created_at: Utc::now(),
+            expires_at: None,
+            tags: Vec::new(),
+            campaign_id: None,
+            track_conversions: false,
+            links: LinkResources {
+                analytics: "http://sho.rt/analytics/abc123".to_string(),
+                qr: "http://sho.rt/links/abc123/qr".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn fingerprint_depends_on_owner_and_body() {
+        let fingerprint = request_fingerprint("api_key:alice", &request("https://example.com")).unwrap();
+        assert_eq!(fingerprint, request_fingerprint("api_key:alice", &request("https://example.com")).unwrap());
+        assert_ne!(fingerprint, request_fingerprint("api_key:bob", &request("https://example.com")).unwrap());
+        assert_ne!(fingerprint, request_fingerprint("api_key:alice", &request("https://example.org")).unwrap());
+    }
+
+    #[test]
+    fn completed_key_with_same_fingerprint_is_replayed() {
+        let replayed = replay_stored(Some(("f1".to_string(), Some(response()))), "f1").unwrap();
+        assert_eq!(replayed.slug, "abc123");
+    }
+
+    #[test]
+    fn key_reused_with_another_body_is_rejected() {
+        let err = replay_stored(Some(("f1".to_string(), Some(response()))), "f2").unwrap_err();
+        assert!(matches!(err, AppError::UnprocessableEntity(message) if message.contains("different request body")));
+    }
+
+    #[test]
+    fn pending_or_vanished_keys_are_rejected() {
+        assert!(matches!(replay_stored(Some(("f1".to_string(), None)), "f1"), Err(AppError::UnprocessableEntity(_))));
+        assert!(matches!(replay_stored(None, "f1"), Err(AppError::UnprocessableEntity(_))));
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use sqlx::PgPool;
+
+use crate::{
+    errors::AppError,
+    models::{
+        analytics::{AccountStats, DateRange, TopLink, TopLinksData, TopLinksRequest},
+        query::QueryMetric,
+    },
+    services::analytics::UNIQUE_VISITOR_DAYS,
+};
+
+// Ranks the links an actor owns over the requested range, with a zero-filled sparkline per link
+pub async fn get_top_links(db: &PgPool, owner: &str, params: &TopLinksRequest) -> Result<TopLinksData, AppError> {
+    let (start, end) = params.range()
+        .map_err(|e| AppError::ValidationError(format!("Date validation error: {}", e)))?;
+    let timezone = params.timezone();
+    let include_bots = params.include_bots.unwrap_or(false);
+    let include_duplicates = params.include_duplicates.unwrap_or(false);
+    let granularity = params.granularity.unwrap_or_default();
+    let unit = granularity.unit();
+    let metric = params.metric();
+
+    let order_by = match metric {
+        QueryMetric::Clicks => "clicks",
+        QueryMetric::Uniques => "uniques",
+    };
+    let ranking_query = format!(
+        "SELECT l.slug, l.target_url, COUNT(*) AS clicks, {} AS uniques
+         FROM links l
+         JOIN clicks c ON c.slug = l.slug
+         WHERE l.owner = $1
+           AND c.timestamp >= ($3::date::timestamp AT TIME ZONE $2)
+           AND c.timestamp < (($4::date + 1)::timestamp AT TIME ZONE $2)
+           AND ($5 OR NOT c.is_bot)
+           AND ($6 OR NOT c.is_duplicate)
+         GROUP BY l.slug, l.target_url
+         ORDER BY {} DESC, l.slug
+         LIMIT $7",
+        UNIQUE_VISITOR_DAYS,
+        order_by
+    );
+
+    let ranked: Vec<(String, String, i64, i64)> = sqlx::query_as(&ranking_query)
+        .bind(owner)
+        .bind(timezone)
+        .bind(start)
+        .bind(end)
+        .bind(include_bots)
+        .bind(include_duplicates)
+        .bind(params.limit())
+        .fetch_all(db)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Top links: {}", e)))?;
+
+    // Local bucket starts, and the same instants in UTC for the response
+    let buckets = sqlx::query!(
+        r#"SELECT bucket AS "local!", bucket AT TIME ZONE $2 AS "start!"
+           FROM generate_series(
+               date_trunc($1, $3::date::timestamp),
+               date_trunc($1, ($4::date + 1)::timestamp - interval '1 microsecond'),
+               ('1 ' || $1)::interval
+           ) AS bucket
+           ORDER BY bucket"#,
+        unit,
+        timezone,
+        start,
+        end
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|e| AppError::DatabaseError(format!("Sparkline buckets: {}", e)))?;
+
+    let slugs: Vec<String> = ranked.iter().map(|(slug, ..)| slug.clone()).collect();
+    let counts = sqlx::query!(
+        r#"SELECT slug, date_trunc($2, timestamp AT TIME ZONE $3) AS "bucket!", COUNT(*) AS "count!"
+           FROM clicks
+           WHERE slug = ANY($1)
+             AND timestamp >= ($4::date::timestamp AT TIME ZONE $3)
+             AND timestamp < (($5::date + 1)::timestamp AT TIME ZONE $3)
+             AND ($6 OR NOT is_bot)
+             AND ($7 OR NOT is_duplicate)
+           GROUP BY 1, 2"#,
+        &slugs,
+        unit,
+        timezone,
+        start,
+        end,
+        include_bots,
+        include_duplicates
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|e| AppError::DatabaseError(format!("Sparklines: {}", e)))?;
+
+    let counts: HashMap<(String, NaiveDateTime), i64> = counts
+        .into_iter()
+        .map(|row| ((row.slug, row.bucket), row.count))
+        .collect();
+
+    let links = ranked
+        .into_iter()
+        .map(|(slug, target_url, clicks, uniques)| {
+            let sparkline = buckets
+                .iter()
+                .map(|bucket| counts.get(&(slug.clone(), bucket.local)).copied().unwrap_or(0))
+                .collect();
+            TopLink { slug, target_url, clicks, uniques, sparkline }
+        })
+        .collect();
+
+    let tz: Tz = timezone.parse().unwrap_or(Tz::UTC);
+    let buckets = buckets
+        .into_iter()
+        .map(|bucket| bucket.start.with_timezone(&tz).to_rfc3339())
+        .collect();
+
+    Ok(TopLinksData {
+        metric,
+        granularity,
+        date_range: DateRange {
+            start: start.format("%Y-%m-%d").to_string(),
+            end: end.format("%Y-%m-%d").to_string(),
+            days: (end - start).num_days() + 1,
+            timezone: timezone.to_string(),
+        },
+        buckets,
+        links,
+        stats: get_account_stats(db, owner, timezone, include_bots, include_duplicates).await?,
+    })
+}
+
+async fn get_account_stats(
+    db: &PgPool,
+    owner: &str,
+    timezone: &str,
+    include_bots: bool,
+    include_duplicates: bool,
+) -> Result<AccountStats, AppError> {
+    let links = sqlx::query!(
+        r#"SELECT COUNT(*) AS "total!",
+                  COUNT(*) FILTER (WHERE expires_at IS NULL OR expires_at > NOW()) AS "active!",
+                  COUNT(*) FILTER (WHERE expires_at <= NOW()) AS "expired!"
+           FROM links WHERE owner = $1"#,
+        owner
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| AppError::DatabaseError(format!("Link counts: {}", e)))?;
+
+    let today_start: DateTime<Utc> = sqlx::query_scalar!(
+        r#"SELECT date_trunc('day', NOW() AT TIME ZONE $1) AT TIME ZONE $1 AS "start!""#,
+        timezone
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| AppError::DatabaseError(format!("Clicks today: {}", e)))?;
+
+    let clicks_today = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!"
+           FROM clicks
+           WHERE slug IN (SELECT slug FROM links WHERE owner = $1)
+             AND timestamp >= $2
+             AND ($3 OR NOT is_bot)
+             AND ($4 OR NOT is_duplicate)"#,
+        owner,
+        today_start,
+        include_bots,
+        include_duplicates
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| AppError::DatabaseError(format!("Clicks today: {}", e)))?;
+
+    Ok(AccountStats {
+        total_links: links.total,
+        active_links: links.active,
+        expired_links: links.expired,
+        clicks_today,
+    })
+}
@@ -1,71 +1,455 @@
-use sqlx::{Error};
-use chrono::{Utc, Duration};
+use sqlx::{Error, Postgres, Transaction};
+use chrono::{DateTime, Utc, Duration};
 
 
 
-use crate::errors::AppError;
+use crate::{
+    errors::AppError,
+    models::{actor::Actor, link::{Link, LinkAlias, LinkListQuery, ResolvedLink, ShortenRequest, UpdateLinkRequest}},
+    services::revision::{get_revision, record_revision, ACTION_CREATED, ACTION_ROLLED_BACK, ACTION_UPDATED},
+    validation::tags::normalize_tags,
+};
 
+const GENERATED_SLUG_ATTEMPTS: usize = 5;
+
+// Runs inside the caller's transaction, so an idempotency key can be reserved alongside the link
 pub async fn create_short_link(
-    db : &sqlx::PgPool,
-    target_url: String,
-    custom_slug: Option<String>,
-    expires_in: Option<String>, 
-) -> Result<String, AppError>{
-    
-    
+    tx: &mut Transaction<'_, Postgres>,
+    request: ShortenRequest,
+    actor: &Actor,
+) -> Result<Link, AppError>{
+    let ShortenRequest { target_url, custom_slug, expires_in, tags, campaign_id, track_conversions } = request;
+
     let slug = match custom_slug {
-        Some(slug) => slug,
-        None => nanoid::nanoid!(6)
+        Some(slug) => {
+            if slug_name_taken(tx, &slug).await? {
+                return Err(AppError::ValidationError("Slug already exists".to_string()));
+            }
+            slug
+        }
+        None => generate_slug(tx).await?,
     };
 
-    //TODO :Check for collision
+    // Store the normalized form so every response reports the same target
+    let target_url = url::Url::parse(&target_url)
+        .map_err(|_| AppError::ValidationError("Invalid target URL".to_string()))?
+        .to_string();
 
     //parse expiry
     let expiry = match expires_in {
-         Some(ref raw) => {
-           let dur = humantime::parse_duration(raw)
-                .map_err(|_| AppError::ValidationError("Invalid expiry format".to_string()))?;
-            Some(Utc::now() + Duration::from_std(dur).unwrap())
-        }
+         Some(ref raw) => Some(parse_expiry(raw)?),
         None => None,
     };
 
-    let res = sqlx::query!(
-        "INSERT INTO links (slug, target_url, expires_at) VALUES ($1, $2, $3)",
+    let tags = normalize_tags(tags.unwrap_or_default());
+    ensure_campaign_owned(tx, campaign_id, actor).await?;
+
+    let link = sqlx::query_as!(
+        Link,
+        "INSERT INTO links (slug, target_url, expires_at, tags, campaign_id, owner, track_conversions) VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING slug, target_url, created_at, expires_at, tags, campaign_id, track_conversions",
         slug,
         target_url,
-        expiry
+        expiry,
+        &tags,
+        campaign_id,
+        actor.name,
+        track_conversions.unwrap_or(false)
     )
-    .execute(db)
-    .await;
-    
-    match res {
-        Ok(_) => Ok(slug),
-        Err(e) => {
-            if let Error::Database(db_err) = &e {
-                if db_err.constraint() == Some("links_slug_key") {
-                    return Err(AppError::ValidationError("Slug already exists".to_string()));
-                }
-            }
-            Err(AppError::DatabaseError(e.to_string()))
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(map_link_write_error)?;
+
+    record_revision(tx, &link, ACTION_CREATED, None, &actor.name).await?;
+
+    Ok(link)
+}
+
+fn parse_expiry(raw: &str) -> Result<DateTime<Utc>, AppError> {
+    let dur = humantime::parse_duration(raw)
+        .map_err(|_| AppError::ValidationError("Invalid expiry format".to_string()))?;
+    Ok(Utc::now() + Duration::from_std(dur).unwrap())
+}
+
+fn map_link_write_error(e: Error) -> AppError {
+    if let Error::Database(db_err) = &e {
+        if db_err.constraint() == Some("links_slug_key") {
+            return AppError::ValidationError("Slug already exists".to_string());
         }
+        if db_err.constraint() == Some("links_campaign_id_fkey") {
+            return AppError::ValidationError("Campaign does not exist".to_string());
+        }
+    }
+    AppError::DatabaseError(e.to_string())
 }
+
+pub async fn update_link(
+    db: &sqlx::PgPool,
+    slug: &str,
+    changes: UpdateLinkRequest,
+    actor: &Actor,
+) -> Result<Link, AppError> {
+    let mut tx = db.begin().await?;
+
+    let current = find_owned_link(&mut tx, slug, actor).await?;
+
+    let target_url = match changes.target_url {
+        Some(raw) => url::Url::parse(&raw)
+            .map_err(|_| AppError::ValidationError("Invalid target URL".to_string()))?
+            .to_string(),
+        None => current.target_url,
+    };
+
+    let expires_at = match changes.expires_in {
+        Some(Some(raw)) => Some(parse_expiry(&raw)?),
+        Some(None) => None,
+        None => current.expires_at,
+    };
+
+    let tags = changes.tags.map(normalize_tags).unwrap_or(current.tags);
+    let campaign_id = match changes.campaign_id {
+        Some(campaign_id) => {
+            ensure_campaign_owned(&mut tx, campaign_id, actor).await?;
+            campaign_id
+        }
+        None => current.campaign_id,
+    };
+    let track_conversions = changes.track_conversions.unwrap_or(current.track_conversions);
+
+    let link = write_link_state(&mut tx, slug, &target_url, expires_at, &tags, campaign_id, track_conversions).await?;
+
+    record_revision(&mut tx, &link, ACTION_UPDATED, None, &actor.name).await?;
+    tx.commit().await?;
+
+    Ok(link)
+}
+
+// Restores the target, expiry and settings captured by an earlier revision
+pub async fn rollback_link(
+    db: &sqlx::PgPool,
+    slug: &str,
+    revision: i32,
+    actor: &Actor,
+) -> Result<Link, AppError> {
+    let mut tx = db.begin().await?;
+
+    find_owned_link(&mut tx, slug, actor).await?;
+    let target = get_revision(&mut tx, slug, revision).await?;
+
+    let link = write_link_state(
+        &mut tx,
+        slug,
+        &target.target_url,
+        target.expires_at,
+        &target.tags,
+        target.campaign_id,
+        target.track_conversions,
+    )
+    .await?;
+
+    record_revision(&mut tx, &link, ACTION_ROLLED_BACK, Some(revision), &actor.name).await?;
+    tx.commit().await?;
+
+    Ok(link)
+}
+
+async fn write_link_state(
+    tx: &mut Transaction<'_, Postgres>,
+    slug: &str,
+    target_url: &str,
+    expires_at: Option<DateTime<Utc>>,
+    tags: &[String],
+    campaign_id: Option<i32>,
+    track_conversions: bool,
+) -> Result<Link, AppError> {
+    sqlx::query_as!(
+        Link,
+        "UPDATE links SET target_url = $2, expires_at = $3, tags = $4, campaign_id = $5, track_conversions = $6
+         WHERE slug = $1
+         RETURNING slug, target_url, created_at, expires_at, tags, campaign_id, track_conversions",
+        slug,
+        target_url,
+        expires_at,
+        tags,
+        campaign_id,
+        track_conversions
+    )
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(map_link_write_error)?
+    .ok_or_else(|| AppError::NotFound(format!("Link '{}' not found", slug)))
+}
+
+pub async fn get_link(
+    db: &sqlx::PgPool,
+    slug: &str,
+) -> Result<Link, AppError> {
+    // Expired links are still returned so clients can inspect them
+    sqlx::query_as!(
+        Link,
+        "SELECT slug, target_url, created_at, expires_at, tags, campaign_id, track_conversions FROM links WHERE slug = $1",
+        slug
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Link '{}' not found", slug)))
+}
+
+// Resolves an alias to its primary link, reporting links of other owners as missing
+pub async fn get_owned_link(
+    db: &sqlx::PgPool,
+    slug: &str,
+    owner: &str,
+) -> Result<Link, AppError> {
+    sqlx::query_as!(
+        Link,
+        "SELECT slug, target_url, created_at, expires_at, tags, campaign_id, track_conversions FROM links
+         WHERE slug = COALESCE((SELECT slug FROM link_aliases WHERE alias = $1), $1) AND owner = $2",
+        slug,
+        owner
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Link '{}' not found", slug)))
+}
+
+pub async fn list_links(
+    db: &sqlx::PgPool,
+    owner: &str,
+    query: &LinkListQuery,
+) -> Result<Vec<Link>, AppError> {
+    let tag = query.tag.as_ref().map(|tag| tag.trim().to_lowercase());
+
+    let links = sqlx::query_as!(
+        Link,
+        "SELECT slug, target_url, created_at, expires_at, tags, campaign_id, track_conversions FROM links
+         WHERE owner = $1
+           AND ($2::text IS NULL OR tags @> ARRAY[$2::text])
+           AND ($3::int IS NULL OR campaign_id = $3)
+         ORDER BY created_at DESC
+         LIMIT $4 OFFSET $5",
+        owner,
+        tag,
+        query.campaign_id,
+        query.limit.unwrap_or(50),
+        query.offset.unwrap_or(0)
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(links)
 }
 
 pub async fn resolve_slug(
     db: &sqlx::PgPool,
     slug: String,
-) -> Result<String, AppError> {
+) -> Result<ResolvedLink, AppError> {
 
     //TODO : Add cache layer to speed up lookups
     // Add rate limiting to prevent abuse
     let link = sqlx::query!(
-        "SELECT target_url FROM links WHERE slug = $1 AND (expires_at IS NULL OR expires_at > NOW())",
+        "SELECT slug, target_url, track_conversions FROM links
+         WHERE slug = COALESCE((SELECT slug FROM link_aliases WHERE alias = $1), $1)
+           AND (expires_at IS NULL OR expires_at > NOW())",
         slug
     )
     .fetch_one(db)
     .await
     .map_err(|e| AppError::NotFound(e.to_string()))?;
 
-    Ok(link.target_url)
-}
\ No newline at end of file
+    let alias = (link.slug != slug).then_some(slug);
+
+    Ok(ResolvedLink {
+        slug: link.slug,
+        alias,
+        target_url: link.target_url,
+        track_conversions: link.track_conversions,
+    })
+}
+
+// Maps an alias to the slug of its primary link, leaving other slugs untouched
+pub async fn primary_slug(
+    db: &sqlx::PgPool,
+    slug: &str,
+) -> Result<String, AppError> {
+    let primary = sqlx::query_scalar!(
+        "SELECT slug FROM link_aliases WHERE alias = $1",
+        slug
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(primary.unwrap_or_else(|| slug.to_string()))
+}
+
+// Generated slugs are short enough to collide now and then; a taken one is replaced by a fresh one
+async fn generate_slug(tx: &mut Transaction<'_, Postgres>) -> Result<String, AppError> {
+    for _ in 0..GENERATED_SLUG_ATTEMPTS {
+        let slug = nanoid::nanoid!(6);
+        if !slug_name_taken(tx, &slug).await? {
+            return Ok(slug);
+        }
+    }
+
+    Err(AppError::InternalServerError("Could not generate a free slug".to_string()))
+}
+
+// Locks the name for the rest of the transaction and reports whether a link or alias uses it
+async fn slug_name_taken(tx: &mut Transaction<'_, Postgres>, name: &str) -> Result<bool, AppError> {
+    lock_slug_name(tx, name).await?;
+
+    let taken = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM links WHERE slug = $1)
+               OR EXISTS(SELECT 1 FROM link_aliases WHERE alias = $1) AS "taken!""#,
+        name
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(taken)
+}
+
+pub async fn create_alias(
+    db: &sqlx::PgPool,
+    slug: &str,
+    alias: &str,
+    actor: &Actor,
+) -> Result<LinkAlias, AppError> {
+    let mut tx = db.begin().await?;
+    lock_slug_name(&mut tx, alias).await?;
+
+    // Make sure the primary exists, belongs to the actor and is not itself an alias
+    let link = find_owned_link(&mut tx, slug, actor).await?;
+
+    let slug_taken = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM links WHERE slug = $1) AS "exists!""#,
+        alias
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if slug_taken {
+        return Err(AppError::ValidationError("Slug already exists".to_string()));
+    }
+
+    let res = sqlx::query_as!(
+        LinkAlias,
+        "INSERT INTO link_aliases (alias, slug) VALUES ($1, $2)
+         RETURNING alias, slug, created_at",
+        alias,
+        link.slug
+    )
+    .fetch_one(&mut *tx)
+    .await;
+
+    match res {
+        Ok(alias) => {
+            tx.commit().await?;
+            Ok(alias)
+        }
+        Err(e) => {
+            if let Error::Database(db_err) = &e {
+                if db_err.constraint() == Some("link_aliases_pkey") {
+                    return Err(AppError::ValidationError("Alias already exists".to_string()));
+                }
+            }
+            Err(AppError::DatabaseError(e.to_string()))
+        }
+    }
+}
+
+// Links can only join campaigns of their own owner; other campaigns are reported as missing
+async fn ensure_campaign_owned(
+    tx: &mut Transaction<'_, Postgres>,
+    campaign_id: Option<i32>,
+    actor: &Actor,
+) -> Result<(), AppError> {
+    let Some(campaign_id) = campaign_id else {
+        return Ok(());
+    };
+
+    let owned = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM campaigns WHERE id = $1 AND owner = $2) AS "owned!""#,
+        campaign_id,
+        actor.name
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    if owned {
+        Ok(())
+    } else {
+        Err(AppError::ValidationError("Campaign does not exist".to_string()))
+    }
+}
+
+// Links and aliases share one namespace across two tables, so a name is locked for the rest of
+// the transaction while it is checked in one table and inserted into the other
+async fn lock_slug_name(tx: &mut Transaction<'_, Postgres>, name: &str) -> Result<(), AppError> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(name)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+// Locks a link for a change by its owner. Links of other owners are reported as missing.
+async fn find_owned_link(
+    tx: &mut Transaction<'_, Postgres>,
+    slug: &str,
+    actor: &Actor,
+) -> Result<Link, AppError> {
+    sqlx::query_as!(
+        Link,
+        "SELECT slug, target_url, created_at, expires_at, tags, campaign_id, track_conversions FROM links
+         WHERE slug = $1 AND owner = $2
+         FOR UPDATE",
+        slug,
+        actor.name
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Link '{}' not found", slug)))
+}
+
+pub async fn list_aliases(
+    db: &sqlx::PgPool,
+    slug: &str,
+    owner: &str,
+) -> Result<Vec<LinkAlias>, AppError> {
+    let link = get_owned_link(db, slug, owner).await?;
+
+    let aliases = sqlx::query_as!(
+        LinkAlias,
+        "SELECT alias, slug, created_at FROM link_aliases WHERE slug = $1 ORDER BY created_at",
+        link.slug
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(aliases)
+}
+
+pub async fn delete_alias(
+    db: &sqlx::PgPool,
+    slug: &str,
+    alias: &str,
+    actor: &Actor,
+) -> Result<(), AppError> {
+    let res = sqlx::query!(
+        "DELETE FROM link_aliases a USING links l
+         WHERE a.slug = l.slug AND a.slug = $1 AND a.alias = $2 AND l.owner = $3",
+        slug,
+        alias,
+        actor.name
+    )
+    .execute(db)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Alias '{}' not found for '{}'", alias, slug)));
+    }
+
+    Ok(())
+}
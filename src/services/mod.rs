@@ -1,2 +1,12 @@
 pub mod link;
-pub mod analytics;
\ No newline at end of file
+pub mod analytics;
+pub mod idempotency;
+pub mod campaign;
+pub mod revision;
+pub mod rollup;
+pub mod realtime;
+pub mod export;
+pub mod query;
+pub mod leaderboard;
+pub mod partition;
+pub mod conversion;
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+
+const PARTITION_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+// Monthly click partitions kept ready after the current month
+const MONTHS_AHEAD: i32 = 3;
+
+pub async fn run_partition_maintenance(db: PgPool) {
+    let mut interval = tokio::time::interval(PARTITION_INTERVAL);
+    loop {
+        interval.tick().await;
+        match create_click_partitions(&db).await {
+            Ok(0) => {}
+            Ok(created) => tracing::info!("Created {} click partitions", created),
+            Err(e) => tracing::error!("Failed to create click partitions: {:?}", e),
+        }
+    }
+}
+
+// Creates any missing partition from the current month through MONTHS_AHEAD; existing ones are kept
+pub async fn create_click_partitions(db: &PgPool) -> Result<i32, AppError> {
+    sqlx::query_scalar!(
+        r#"SELECT create_click_partitions(CURRENT_DATE, $1) AS "created!""#,
+        MONTHS_AHEAD
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| AppError::DatabaseError(format!("Click partitions: {}", e)))
+}
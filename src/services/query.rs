@@ -0,0 +1,318 @@
+use chrono::NaiveDate;
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::{
+    errors::AppError,
+    models::query::{AnalyticsQuery, FilterOperator, QueryDimension, QueryMetric, QueryResult, SortDirection},
+    services::{analytics::UNIQUE_VISITOR_DAYS, link::get_owned_link},
+};
+
+// Values a single `in` / `not_in` filter may list
+const MAX_FILTER_VALUES: usize = 100;
+
+// Positional parameters of the compiled query; every value is bound as text
+struct QueryParams(Vec<String>);
+
+impl QueryParams {
+    fn bind(&mut self, value: impl Into<String>) -> String {
+        self.0.push(value.into());
+        format!("${}", self.0.len())
+    }
+}
+
+// SQL for a whitelisted dimension; `$1` is always the requested timezone
+fn dimension_expression(dimension: QueryDimension, unit: &str) -> String {
+    match dimension {
+        QueryDimension::Slug => "slug".to_string(),
+        QueryDimension::Variant => "COALESCE(alias, slug)".to_string(),
+        QueryDimension::ReferrerDomain => "COALESCE(referrer_domain, 'Unknown')".to_string(),
+        QueryDimension::Channel => "COALESCE(channel, 'Unknown')".to_string(),
+        QueryDimension::Browser => "COALESCE(browser_family, 'Unknown')".to_string(),
+        QueryDimension::Os => "COALESCE(os_family, 'Unknown')".to_string(),
+        QueryDimension::Device => "COALESCE(device_type, 'Unknown')".to_string(),
+        QueryDimension::Country => "COALESCE(country, 'Unknown')".to_string(),
+        QueryDimension::Date => format!(
+            "to_char(date_trunc('{}', timestamp AT TIME ZONE $1), 'YYYY-MM-DD\"T\"HH24:MI:SS')",
+            unit
+        ),
+    }
+}
+
+fn metric_expression(metric: QueryMetric) -> &'static str {
+    match metric {
+        QueryMetric::Clicks => "COUNT(*)",
+        // Visitors per UTC day, summed; see UNIQUE_VISITOR_DAYS
+        QueryMetric::Uniques => UNIQUE_VISITOR_DAYS,
+    }
+}
+
+fn validate_query(query: &AnalyticsQuery) -> Result<(), AppError> {
+    let mut names: Vec<&str> = query.dimensions.iter().map(QueryDimension::name).collect();
+    names.extend(query.metrics.iter().map(QueryMetric::name));
+    if (1..names.len()).any(|i| names[..i].contains(&names[i])) {
+        return Err(AppError::ValidationError("Dimensions and metrics must not repeat".to_string()));
+    }
+
+    if let Some(order) = query.order_by.iter().find(|order| !names.contains(&order.field.as_str())) {
+        return Err(AppError::ValidationError(format!(
+            "Cannot order by '{}': only requested dimensions and metrics are allowed",
+            order.field
+        )));
+    }
+
+    for filter in &query.filters {
+        if filter.dimension == QueryDimension::Date {
+            return Err(AppError::ValidationError("Filter dates with start_date and end_date".to_string()));
+        }
+
+        let values = filter.value.values();
+        let single = matches!(filter.op, FilterOperator::Eq | FilterOperator::Neq);
+        if values.is_empty() || values.len() > MAX_FILTER_VALUES || (single && values.len() != 1) {
+            return Err(AppError::ValidationError(format!(
+                "Filter on '{}' needs one value for eq/neq and 1 to {} values for in/not_in",
+                filter.dimension.name(),
+                MAX_FILTER_VALUES
+            )));
+        }
+    }
+
+    if let (Some(start), Some(end)) = (&query.start_date, &query.end_date) {
+        if let (Ok(start), Ok(end)) = (NaiveDate::parse_from_str(start, "%Y-%m-%d"), NaiveDate::parse_from_str(end, "%Y-%m-%d")) {
+            if end < start {
+                return Err(AppError::ValidationError("End date cannot be before start date".to_string()));
+            }
+            if (end - start).num_days() > 366 {
+                return Err(AppError::ValidationError("Date range cannot exceed 1 year".to_string()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Compiles the query into parameterized SQL over `clicks`; only whitelisted expressions are interpolated
+// Only links owned by `owner` are aggregated, whichever slugs or campaign are asked for
+pub async fn run_query(db: &PgPool, owner: &str, query: &AnalyticsQuery) -> Result<QueryResult, AppError> {
+    validate_query(query)?;
+
+    let unit = query.granularity.unwrap_or_default().unit();
+    let mut params = QueryParams(vec![query.timezone().to_string()]);
+    let mut conditions = vec![format!("slug IN (SELECT slug FROM links WHERE owner = {})", params.bind(owner))];
+
+    if let Some(slugs) = &query.slugs {
+        // Aliases are queried under their primary link
+        let mut placeholders = Vec::with_capacity(slugs.len());
+        for slug in slugs {
+            placeholders.push(params.bind(get_owned_link(db, slug, owner).await?.slug));
+        }
+        conditions.push(format!("slug IN ({})", placeholders.join(", ")));
+    }
+
+    if let Some(campaign_id) = query.campaign_id {
+        conditions.push(format!(
+            "slug IN (SELECT slug FROM links WHERE campaign_id = {}::int)",
+            params.bind(campaign_id.to_string())
+        ));
+    }
+
+    if let Some(start_date) = &query.start_date {
+        // Half-open range on the raw timestamp, so the (slug, timestamp) index applies
+        conditions.push(format!("timestamp >= ({}::date::timestamp AT TIME ZONE $1)", params.bind(start_date.as_str())));
+    }
+    if let Some(end_date) = &query.end_date {
+        conditions.push(format!("timestamp < (({}::date + 1)::timestamp AT TIME ZONE $1)", params.bind(end_date.as_str())));
+    }
+
+    if !query.include_bots.unwrap_or(false) {
+        conditions.push("NOT is_bot".to_string());
+    }
+    if !query.include_duplicates.unwrap_or(false) {
+        conditions.push("NOT is_duplicate".to_string());
+    }
+
+    for filter in &query.filters {
+        let expression = dimension_expression(filter.dimension, unit);
+        let placeholders: Vec<String> = filter.value.values().into_iter().map(|value| params.bind(value)).collect();
+
+        conditions.push(match filter.op {
+            FilterOperator::Eq => format!("{} = {}", expression, placeholders[0]),
+            FilterOperator::Neq => format!("{} <> {}", expression, placeholders[0]),
+            FilterOperator::In => format!("{} IN ({})", expression, placeholders.join(", ")),
+            FilterOperator::NotIn => format!("{} NOT IN ({})", expression, placeholders.join(", ")),
+        });
+    }
+
+    let mut columns: Vec<String> = query.dimensions
+        .iter()
+        .map(|dimension| format!("{} AS \"{}\"", dimension_expression(*dimension, unit), dimension.name()))
+        .collect();
+    columns.extend(query.metrics.iter().map(|metric| format!("{} AS \"{}\"", metric_expression(*metric), metric.name())));
+
+    let group_by = if query.dimensions.is_empty() {
+        String::new()
+    } else {
+        let positions: Vec<String> = (1..=query.dimensions.len()).map(|i| i.to_string()).collect();
+        format!("GROUP BY {}", positions.join(", "))
+    };
+
+    // Without an explicit order the first metric ranks rows, dimensions break ties
+    let mut order_by: Vec<String> = if query.order_by.is_empty() {
+        vec![format!("\"{}\" DESC", query.metrics[0].name())]
+    } else {
+        query.order_by
+            .iter()
+            .map(|order| {
+                let direction = match order.direction {
+                    SortDirection::Asc => "ASC",
+                    SortDirection::Desc => "DESC",
+                };
+                format!("\"{}\" {}", order.field, direction)
+            })
+            .collect()
+    };
+    order_by.extend(query.dimensions.iter().map(|dimension| format!("\"{}\" ASC", dimension.name())));
+
+    let where_clause = if conditions.is_empty() {
+        "TRUE".to_string()
+    } else {
+        conditions.join(" AND ")
+    };
+
+    let sql = format!(
+        "SELECT {} FROM clicks WHERE {} {} ORDER BY {} LIMIT {}",
+        columns.join(", "),
+        where_clause,
+        group_by,
+        order_by.join(", "),
+        query.limit()
+    );
+
+    let mut query_builder = sqlx::query(&sql);
+    for param in &params.0 {
+        query_builder = query_builder.bind(param);
+    }
+
+    let rows: Vec<PgRow> = query_builder
+        .fetch_all(db)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Analytics query: {}", e)))?;
+
+    let rows = rows
+        .iter()
+        .map(|row| {
+            let mut object = serde_json::Map::new();
+            for (index, dimension) in query.dimensions.iter().enumerate() {
+                let value: String = row.try_get(index)?;
+                object.insert(dimension.name().to_string(), value.into());
+            }
+            for (index, metric) in query.metrics.iter().enumerate() {
+                let value: i64 = row.try_get(query.dimensions.len() + index)?;
+                object.insert(metric.name().to_string(), value.into());
+            }
+            Ok(object)
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(|e| AppError::DatabaseError(format!("Analytics query: {}", e)))?;
+
+    Ok(QueryResult {
+        dimensions: query.dimensions.clone(),
+        metrics: query.metrics.clone(),
+        timezone: query.timezone().to_string(),
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn query(body: serde_json::Value) -> AnalyticsQuery {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn accepts_a_whitelisted_query() {
+        let query = query(json!({
+            "dimensions": ["channel", "date"],
+            "metrics": ["clicks", "uniques"],
+            "filters": [
+                { "dimension": "country", "op": "in", "value": ["DE", "FR"] },
+                { "dimension": "device", "op": "neq", "value": "bot" }
+            ],
+            "order_by": [{ "field": "uniques", "direction": "asc" }],
+            "start_date": "2026-01-01",
+            "end_date": "2026-01-31"
+        }));
+        assert!(validate_query(&query).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_dimensions_and_metrics_when_parsing() {
+        let unknown_dimension = json!({ "dimensions": ["ip"], "metrics": ["clicks"] });
+        assert!(serde_json::from_value::<AnalyticsQuery>(unknown_dimension).is_err());
+
+        let unknown_metric = json!({ "metrics": ["revenue; DROP TABLE clicks"] });
+        assert!(serde_json::from_value::<AnalyticsQuery>(unknown_metric).is_err());
+    }
+
+    #[test]
+    fn rejects_repeated_fields() {
+        let query = query(json!({ "dimensions": ["browser", "browser"], "metrics": ["clicks"] }));
+        assert!(validate_query(&query).is_err());
+    }
+
+    #[test]
+    fn only_orders_by_requested_fields() {
+        let unrequested = query(json!({
+            "dimensions": ["browser"],
+            "metrics": ["clicks"],
+            "order_by": [{ "field": "os" }]
+        }));
+        assert!(validate_query(&unrequested).is_err());
+
+        let injected = query(json!({ "metrics": ["clicks"], "order_by": [{ "field": "clicks\" DESC; --" }] }));
+        assert!(validate_query(&injected).is_err());
+    }
+
+    #[test]
+    fn checks_filter_value_counts() {
+        let eq_with_many = query(json!({
+            "metrics": ["clicks"],
+            "filters": [{ "dimension": "os", "op": "eq", "value": ["iOS", "Android"] }]
+        }));
+        assert!(validate_query(&eq_with_many).is_err());
+
+        let empty_in = query(json!({
+            "metrics": ["clicks"],
+            "filters": [{ "dimension": "os", "op": "in", "value": [] }]
+        }));
+        assert!(validate_query(&empty_in).is_err());
+
+        let too_many: Vec<String> = (0..=MAX_FILTER_VALUES).map(|i| format!("v{}", i)).collect();
+        let too_many = query(json!({
+            "metrics": ["clicks"],
+            "filters": [{ "dimension": "os", "op": "not_in", "value": too_many }]
+        }));
+        assert!(validate_query(&too_many).is_err());
+    }
+
+    #[test]
+    fn dates_are_filtered_with_the_range_only() {
+        let date_filter = query(json!({
+            "metrics": ["clicks"],
+            "filters": [{ "dimension": "date", "op": "eq", "value": "2026-01-01" }]
+        }));
+        assert!(validate_query(&date_filter).is_err());
+    }
+
+    #[test]
+    fn checks_the_date_range() {
+        let range = |start: &str, end: &str| query(json!({ "metrics": ["clicks"], "start_date": start, "end_date": end }));
+
+        assert!(validate_query(&range("2026-02-01", "2026-01-01")).is_err());
+        assert!(validate_query(&range("2025-01-01", "2026-01-02")).is_ok());
+        assert!(validate_query(&range("2025-01-01", "2026-01-03")).is_err());
+    }
+}
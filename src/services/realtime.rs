@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use redis::{aio::MultiplexedConnection, AsyncCommands};
+
+use crate::{
+    errors::AppError,
+    models::{analytics::{RealtimeData, RealtimeWindow}, click::ClickEvent},
+};
+
+const KEY_PREFIX: &str = "realtime";
+
+// A trailing window made of `buckets` fixed-size buckets, the current one included
+struct Window {
+    bucket_secs: i64,
+    buckets: i64,
+}
+
+const LAST_MINUTE: Window = Window { bucket_secs: 10, buckets: 6 };
+const LAST_HOUR: Window = Window { bucket_secs: 60, buckets: 60 };
+const LAST_DAY: Window = Window { bucket_secs: 3600, buckets: 24 };
+const WINDOWS: [&Window; 3] = [&LAST_MINUTE, &LAST_HOUR, &LAST_DAY];
+
+// Calendar-day (UTC) structures are kept a little longer than the day itself
+const DAY_TTL_SECS: i64 = 2 * 24 * 3600;
+
+impl Window {
+    fn ttl_secs(&self) -> i64 {
+        self.bucket_secs * (self.buckets + 1)
+    }
+
+    fn bucket_key(&self, slug: &str, bucket: i64, kind: &str) -> String {
+        format!("{}:{}:{}s:{}:{}", KEY_PREFIX, slug, self.bucket_secs, bucket, kind)
+    }
+
+    fn keys(&self, slug: &str, now: DateTime<Utc>, kind: &str) -> Vec<String> {
+        let current = now.timestamp() / self.bucket_secs;
+        (0..self.buckets)
+            .map(|offset| self.bucket_key(slug, current - offset, kind))
+            .collect()
+    }
+}
+
+fn day_key(slug: &str, now: DateTime<Utc>, kind: &str) -> String {
+    format!("{}:{}:day:{}:{}", KEY_PREFIX, slug, now.format("%Y-%m-%d"), kind)
+}
+
+// Bumps the click counters and adds the visitor to the HyperLogLogs of every window
+pub async fn record_click(conn: &mut MultiplexedConnection, event: &ClickEvent) -> redis::RedisResult<()> {
+    let visitor = format!("{}|{}", event.ip, event.user_agent);
+    let mut pipe = redis::pipe();
+
+    for window in WINDOWS {
+        let bucket = event.timestamp.timestamp() / window.bucket_secs;
+        let clicks_key = window.bucket_key(&event.slug, bucket, "clicks");
+        let visitors_key = window.bucket_key(&event.slug, bucket, "visitors");
+
+        pipe.incr(&clicks_key, 1).ignore()
+            .expire(&clicks_key, window.ttl_secs()).ignore()
+            .pfadd(&visitors_key, &visitor).ignore()
+            .expire(&visitors_key, window.ttl_secs()).ignore();
+    }
+
+    let clicks_key = day_key(&event.slug, event.timestamp, "clicks");
+    let visitors_key = day_key(&event.slug, event.timestamp, "visitors");
+    pipe.incr(&clicks_key, 1).ignore()
+        .expire(&clicks_key, DAY_TTL_SECS).ignore()
+        .pfadd(&visitors_key, &visitor).ignore()
+        .expire(&visitors_key, DAY_TTL_SECS).ignore();
+
+    pipe.query_async(conn).await
+}
+
+pub async fn get_realtime_data(conn: &mut MultiplexedConnection, slug: String) -> Result<RealtimeData, AppError> {
+    let now = Utc::now();
+
+    let last_minute = window_counts(conn, LAST_MINUTE.keys(&slug, now, "clicks"), LAST_MINUTE.keys(&slug, now, "visitors")).await?;
+    let last_hour = window_counts(conn, LAST_HOUR.keys(&slug, now, "clicks"), LAST_HOUR.keys(&slug, now, "visitors")).await?;
+    let last_day = window_counts(conn, LAST_DAY.keys(&slug, now, "clicks"), LAST_DAY.keys(&slug, now, "visitors")).await?;
+    let today = window_counts(conn, vec![day_key(&slug, now, "clicks")], vec![day_key(&slug, now, "visitors")]).await?;
+
+    Ok(RealtimeData {
+        slug,
+        last_minute,
+        last_hour,
+        last_day,
+        today,
+        generated_at: now.to_rfc3339(),
+    })
+}
+
+// Sums the click counters and counts the union of the visitor HyperLogLogs
+async fn window_counts(
+    conn: &mut MultiplexedConnection,
+    clicks_keys: Vec<String>,
+    visitors_keys: Vec<String>,
+) -> Result<RealtimeWindow, AppError> {
+    let counts: Vec<Option<i64>> = redis::cmd("MGET")
+        .arg(&clicks_keys)
+        .query_async(conn)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Realtime clicks: {}", e)))?;
+
+    let unique_visitors: i64 = conn.pfcount(&visitors_keys).await
+        .map_err(|e| AppError::InternalServerError(format!("Realtime visitors: {}", e)))?;
+
+    Ok(RealtimeWindow {
+        clicks: counts.into_iter().flatten().sum(),
+        unique_visitors,
+    })
+}
@@ -0,0 +1,71 @@
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::{errors::AppError, models::link::{Link, LinkRevision}};
+
+pub const ACTION_CREATED: &str = "created";
+pub const ACTION_UPDATED: &str = "updated";
+pub const ACTION_ROLLED_BACK: &str = "rolled_back";
+
+// Appends a snapshot of `link` as its next revision
+pub async fn record_revision(
+    tx: &mut Transaction<'_, Postgres>,
+    link: &Link,
+    action: &str,
+    source_revision: Option<i32>,
+    actor: &str,
+) -> Result<LinkRevision, AppError> {
+    let revision = sqlx::query_as!(
+        LinkRevision,
+        "INSERT INTO link_revisions (slug, revision, action, target_url, expires_at, tags, campaign_id, track_conversions, source_revision, actor)
+         SELECT $1::varchar, COALESCE(MAX(revision), 0) + 1, $2, $3, $4, $5, $6, $7, $8, $9
+         FROM link_revisions WHERE slug = $1
+         RETURNING slug, revision, action, target_url, expires_at, tags, campaign_id, track_conversions, source_revision, actor, created_at",
+        link.slug,
+        action,
+        link.target_url,
+        link.expires_at,
+        &link.tags,
+        link.campaign_id,
+        link.track_conversions,
+        source_revision,
+        actor
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(revision)
+}
+
+pub async fn list_revisions(db: &PgPool, slug: &str) -> Result<Vec<LinkRevision>, AppError> {
+    let revisions = sqlx::query_as!(
+        LinkRevision,
+        "SELECT slug, revision, action, target_url, expires_at, tags, campaign_id, track_conversions, source_revision, actor, created_at
+         FROM link_revisions WHERE slug = $1 ORDER BY revision DESC",
+        slug
+    )
+    .fetch_all(db)
+    .await?;
+
+    if revisions.is_empty() {
+        return Err(AppError::NotFound(format!("No history found for '{}'", slug)));
+    }
+
+    Ok(revisions)
+}
+
+pub async fn get_revision(
+    tx: &mut Transaction<'_, Postgres>,
+    slug: &str,
+    revision: i32,
+) -> Result<LinkRevision, AppError> {
+    sqlx::query_as!(
+        LinkRevision,
+        "SELECT slug, revision, action, target_url, expires_at, tags, campaign_id, track_conversions, source_revision, actor, created_at
+         FROM link_revisions WHERE slug = $1 AND revision = $2",
+        slug,
+        revision
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Revision {} not found for '{}'", revision, slug)))
+}
@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+
+pub const DIMENSION_TOTAL: &str = "total";
+pub const DIMENSION_REFERRER: &str = "referrer";
+pub const DIMENSION_BROWSER: &str = "browser";
+pub const DIMENSION_REFERRER_DOMAIN: &str = "referrer_domain";
+pub const DIMENSION_CHANNEL: &str = "channel";
+pub const DIMENSION_USER_AGENT: &str = "user_agent";
+pub const DIMENSION_BROWSER_VERSION: &str = "browser_version";
+pub const DIMENSION_OS: &str = "os";
+pub const DIMENSION_DEVICE: &str = "device";
+pub const DIMENSION_REGION: &str = "region";
+pub const DIMENSION_CITY: &str = "city";
+pub const DIMENSION_ALIAS: &str = "alias";
+pub const DIMENSION_BOT_REASON: &str = "bot_reason";
+pub const DIMENSION_BOT_NAME: &str = "bot_name";
+pub const DIMENSION_VISITOR: &str = "visitor";
+
+const DIMENSIONS: [&str; 15] = [
+    DIMENSION_TOTAL,
+    DIMENSION_REFERRER,
+    DIMENSION_BROWSER,
+    DIMENSION_REFERRER_DOMAIN,
+    DIMENSION_CHANNEL,
+    DIMENSION_USER_AGENT,
+    DIMENSION_BROWSER_VERSION,
+    DIMENSION_OS,
+    DIMENSION_DEVICE,
+    DIMENSION_REGION,
+    DIMENSION_CITY,
+    DIMENSION_ALIAS,
+    DIMENSION_BOT_REASON,
+    DIMENSION_BOT_NAME,
+    DIMENSION_VISITOR,
+];
+
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(60);
+// Clicks folded per transaction, so a large backlog does not hold one long lock
+const ROLLUP_BATCH_SIZE: i64 = 50_000;
+// Clicks are only folded once ingested this long ago, so inserts still in flight have committed
+const ROLLUP_SAFETY_LAG: Duration = Duration::from_secs(5 * 60);
+
+// Rollup tables and the bucket unit they are truncated to
+const ROLLUP_TABLES: [(&str, &str); 2] = [
+    ("click_rollups_hourly", "hour"),
+    ("click_rollups_daily", "day"),
+];
+
+// Which table analytics counts are read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickSource {
+    Raw,
+    Hourly,
+    Daily,
+}
+
+impl ClickSource {
+    // Rollup buckets are UTC hours and days, so they only line up with the requested
+    // local buckets when the timezone is whole-hour (hourly) or UTC itself (daily)
+    pub fn for_request(timezone: &str, unit: &str) -> Self {
+        let Ok(tz) = timezone.parse::<Tz>() else {
+            return ClickSource::Raw;
+        };
+
+        if unit == "minute" || !has_whole_hour_offsets(tz) {
+            ClickSource::Raw
+        } else if tz == Tz::UTC && unit != "hour" {
+            ClickSource::Daily
+        } else {
+            ClickSource::Hourly
+        }
+    }
+
+    // Subquery exposing `slug, timestamp, is_bot, is_duplicate, country, value, count` for one dimension,
+    // so the regular analytics filter clause applies to it unchanged
+    pub fn subquery(&self, dimension: &str) -> String {
+        let raw_clicks = format!(
+            "SELECT slug, timestamp, is_bot, is_duplicate, country, {} AS value, 1::bigint AS count FROM clicks",
+            raw_value(dimension)
+        );
+
+        let table = match self {
+            ClickSource::Raw => return format!("({}) AS clicks", raw_clicks),
+            ClickSource::Hourly => "click_rollups_hourly",
+            ClickSource::Daily => "click_rollups_daily",
+        };
+
+        format!(
+            "(SELECT slug, timestamp, is_bot, is_duplicate, NULLIF(country, '') AS country, value, count
+              FROM {} WHERE dimension = '{}'
+              UNION ALL
+              {} WHERE (ingested_at, id) > (SELECT rolled_up_until, last_click_id FROM rollup_state)) AS clicks",
+            table, dimension, raw_clicks
+        )
+    }
+}
+
+// Value of a dimension computed from a clicks row; missing values are reported as 'Unknown'
+fn raw_value(dimension: &str) -> &'static str {
+    match dimension {
+        DIMENSION_REFERRER => "COALESCE(referer, 'Unknown')",
+        DIMENSION_BROWSER => "COALESCE(browser_family, 'Unknown')",
+        DIMENSION_REFERRER_DOMAIN => "COALESCE(referrer_domain, 'Unknown')",
+        DIMENSION_CHANNEL => "COALESCE(channel, 'Unknown')",
+        DIMENSION_USER_AGENT => "COALESCE(user_agent, 'Unknown')",
+        DIMENSION_BROWSER_VERSION => "COALESCE(browser_family || COALESCE(' ' || browser_version, ''), 'Unknown')",
+        DIMENSION_OS => "COALESCE(os_family, 'Unknown')",
+        DIMENSION_DEVICE => "COALESCE(device_type, 'Unknown')",
+        // Regions and cities are qualified by country
+        DIMENSION_REGION => "COALESCE(region || ', ' || country, 'Unknown')",
+        DIMENSION_CITY => "COALESCE(city || ', ' || country, 'Unknown')",
+        // The alias used to reach the link, or the slug itself
+        DIMENSION_ALIAS => "COALESCE(alias, slug)",
+        DIMENSION_BOT_REASON => "COALESCE(bot_reason, 'Unknown')",
+        DIMENSION_BOT_NAME => "COALESCE(bot_name, 'Unknown')",
+        // Clicks recorded before visitors were tracked are neither new nor returning
+        DIMENSION_VISITOR => "CASE WHEN is_returning THEN 'returning' WHEN NOT is_returning THEN 'new' ELSE 'unknown' END",
+        _ => "''",
+    }
+}
+
+// Rollups cover clicks of any age, so the offset is checked on the first of every month from last
+// year through next year. That covers DST shifts and recent changes to a zone's offset.
+fn has_whole_hour_offsets(tz: Tz) -> bool {
+    let year = Utc::now().year();
+    (year - 1..=year + 1).all(|year| {
+        (1..=12).all(|month| {
+            tz.with_ymd_and_hms(year, month, 1, 12, 0, 0)
+                .single()
+                .is_some_and(|dt| dt.offset().fix().local_minus_utc() % 3600 == 0)
+        })
+    })
+}
+
+pub async fn run_rollups(db: PgPool) {
+    let mut interval = tokio::time::interval(ROLLUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        loop {
+            match refresh_rollups(&db).await {
+                Ok(0) => break,
+                Ok(rolled_up) => tracing::info!("Rolled up {} clicks", rolled_up),
+                Err(e) => {
+                    tracing::error!("Failed to refresh click rollups: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Folds the next batch of clicks into the hourly and daily rollups and returns how many were added
+pub async fn refresh_rollups(db: &PgPool) -> Result<i64, AppError> {
+    let mut tx = db.begin().await
+        .map_err(|e| AppError::DatabaseError(format!("Failed to start transaction: {}", e)))?;
+
+    // Locks the watermark so concurrent instances never fold the same clicks twice. The watermark
+    // is the (ingested_at, id) of the last folded click; ingestion times are assigned at insert,
+    // so only clicks older than the safety lag are folded, by which time their inserts committed.
+    let watermark: (DateTime<Utc>, i64) =
+        sqlx::query_as("SELECT rolled_up_until, last_click_id FROM rollup_state FOR UPDATE")
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Rollup state: {}", e)))?;
+
+    let batch: Option<(DateTime<Utc>, i64, i64)> = sqlx::query_as(
+        "SELECT ingested_at, id, COUNT(*) OVER () FROM (
+             SELECT ingested_at, id FROM clicks
+             WHERE (ingested_at, id) > ($1, $2)
+               AND ingested_at < NOW() - make_interval(secs => $3)
+             ORDER BY ingested_at, id
+             LIMIT $4
+         ) AS batch
+         ORDER BY ingested_at DESC, id DESC
+         LIMIT 1",
+    )
+    .bind(watermark.0)
+    .bind(watermark.1)
+    .bind(ROLLUP_SAFETY_LAG.as_secs_f64())
+    .bind(ROLLUP_BATCH_SIZE)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| AppError::DatabaseError(format!("Rollup batch: {}", e)))?;
+
+    let Some((next_ingested_at, next_click_id, rolled_up)) = batch else {
+        return Ok(0);
+    };
+
+    let dimension_values = DIMENSIONS
+        .iter()
+        .map(|dimension| format!("('{}', {})", dimension, raw_value(dimension)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    for (table, unit) in ROLLUP_TABLES {
+        let query = format!(
+            "INSERT INTO {table} (slug, timestamp, dimension, value, country, is_bot, is_duplicate, count)
+             SELECT slug, date_trunc('{unit}', timestamp AT TIME ZONE 'UTC') AT TIME ZONE 'UTC',
+                    d.dimension, d.value, COALESCE(country, ''), is_bot, is_duplicate, COUNT(*)
+             FROM clicks
+             CROSS JOIN LATERAL (VALUES {dimension_values}) AS d(dimension, value)
+             WHERE (ingested_at, id) > ($1, $2) AND (ingested_at, id) <= ($3, $4)
+             GROUP BY 1, 2, 3, 4, 5, 6, 7
+             ON CONFLICT (slug, timestamp, dimension, value, country, is_bot, is_duplicate)
+             DO UPDATE SET count = {table}.count + EXCLUDED.count",
+        );
+
+        sqlx::query(&query)
+            .bind(watermark.0)
+            .bind(watermark.1)
+            .bind(next_ingested_at)
+            .bind(next_click_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::DatabaseError(format!("Rollup {}: {}", table, e)))?;
+    }
+
+    sqlx::query!(
+        "UPDATE rollup_state SET rolled_up_until = $1, last_click_id = $2, updated_at = NOW()",
+        next_ingested_at,
+        next_click_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::DatabaseError(format!("Rollup state: {}", e)))?;
+
+    tx.commit().await
+        .map_err(|e| AppError::DatabaseError(format!("Failed to commit transaction: {}", e)))?;
+
+    Ok(rolled_up)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utc_reads_daily_rollups_except_for_hourly_buckets() {
+        assert_eq!(ClickSource::for_request("UTC", "day"), ClickSource::Daily);
+        assert_eq!(ClickSource::for_request("UTC", "week"), ClickSource::Daily);
+        assert_eq!(ClickSource::for_request("UTC", "hour"), ClickSource::Hourly);
+    }
+
+    #[test]
+    fn whole_hour_timezones_read_hourly_rollups() {
+        assert_eq!(ClickSource::for_request("America/New_York", "day"), ClickSource::Hourly);
+        assert_eq!(ClickSource::for_request("Europe/Berlin", "hour"), ClickSource::Hourly);
+    }
+
+    #[test]
+    fn minutes_fractional_offsets_and_unknown_zones_read_raw_clicks() {
+        assert_eq!(ClickSource::for_request("UTC", "minute"), ClickSource::Raw);
+        assert_eq!(ClickSource::for_request("Asia/Kolkata", "day"), ClickSource::Raw);
+        assert_eq!(ClickSource::for_request("Not/AZone", "day"), ClickSource::Raw);
+    }
+
+    #[test]
+    fn offsets_are_checked_across_dst() {
+        assert!(has_whole_hour_offsets(Tz::UTC));
+        assert!(has_whole_hour_offsets(Tz::Europe__London));
+        assert!(!has_whole_hour_offsets(Tz::Asia__Kathmandu));
+        // +10:30 in winter and +11 in summer, so only one half of the year is fractional
+        assert!(!has_whole_hour_offsets(Tz::Australia__Lord_Howe));
+    }
+
+    #[test]
+    fn every_dimension_but_total_has_a_raw_value() {
+        for dimension in DIMENSIONS {
+            assert_eq!(raw_value(dimension) == "''", dimension == DIMENSION_TOTAL, "{}", dimension);
+        }
+    }
+
+    #[test]
+    fn rollup_subqueries_read_the_dimension_and_unfolded_clicks() {
+        let subquery = ClickSource::Hourly.subquery(DIMENSION_OS);
+        assert!(subquery.contains("FROM click_rollups_hourly WHERE dimension = 'os'"));
+        assert!(subquery.contains("COALESCE(os_family, 'Unknown') AS value"));
+        assert!(subquery.contains("(SELECT rolled_up_until, last_click_id FROM rollup_state)"));
+
+        let raw = ClickSource::Raw.subquery(DIMENSION_OS);
+        assert!(!raw.contains("click_rollups"));
+    }
+}
@@ -3,7 +3,7 @@ use std::time::Duration;
 use redis::aio::MultiplexedConnection;
 use sqlx::PgPool;
 use tokio::time::sleep;
-use crate::{models::click::ClickEvent, streams::{producer::STREAM_KEY, retry::{insert_click_with_retry, RETRY_DELAY_MS}}};
+use crate::{enrichment::ClickEnricher, models::click::{ClickEnrichment, ClickEvent}, streams::{dedupe::ClickDeduper, live::publish_live_click, producer::STREAM_KEY, retry::{insert_click_with_retry, RETRY_DELAY_MS}}};
 
 const CONSUMER_GROUP: &str = "click_consumers";
 const CONSUMER_NAME: &str = "linkping_consumer";
@@ -11,7 +11,12 @@ const BLOCK_TIME_MS: u64 = 5000;
 const BATCH_SIZE: u64 = 10;
 
 
-pub async fn consume_click_events(db: &PgPool, mut conn: MultiplexedConnection) -> redis::RedisResult<()> {
+pub async fn consume_click_events(
+    db: &PgPool,
+    mut conn: MultiplexedConnection,
+    enricher: ClickEnricher,
+    deduper: ClickDeduper,
+) -> redis::RedisResult<()> {
     // Initialize consumer group
     initialize_consumer_group(&mut conn).await?;
 
@@ -21,7 +26,7 @@ pub async fn consume_click_events(db: &PgPool, mut conn: MultiplexedConnection)
             Ok(reply) => {
                 // Process messages if any were received
                 if !reply.keys.is_empty() {
-                    process_stream_reply(db, &mut conn, reply).await;
+                    process_stream_reply(db, &mut conn, &enricher, &deduper, reply).await;
                 }
             },
             Err(e) => {
@@ -80,10 +85,16 @@ async fn read_stream_messages(conn: &mut MultiplexedConnection) -> redis::RedisR
         .await
 }
 
-async fn process_stream_reply(db: &PgPool, conn: &mut MultiplexedConnection, reply: redis::streams::StreamReadReply) {
+async fn process_stream_reply(
+    db: &PgPool,
+    conn: &mut MultiplexedConnection,
+    enricher: &ClickEnricher,
+    deduper: &ClickDeduper,
+    reply: redis::streams::StreamReadReply,
+) {
     for stream_key in reply.keys {
         for stream_id in stream_key.ids {
-            process_stream_message(db, conn, &stream_key.key, &stream_id).await;
+            process_stream_message(db, conn, enricher, deduper, &stream_key.key, &stream_id).await;
         }
     }
 }
@@ -91,19 +102,34 @@ async fn process_stream_reply(db: &PgPool, conn: &mut MultiplexedConnection, rep
 async fn process_stream_message(
     db: &PgPool,
     conn: &mut MultiplexedConnection,
+    enricher: &ClickEnricher,
+    deduper: &ClickDeduper,
     stream_key: &str,
     stream_id: &redis::streams::StreamId,
 ) {
     for (_field, value) in stream_id.map.iter() {
         if let Some(event) = parse_event_from_value(value) {
             tracing::info!("Processing click event for slug: {}", event.slug);
-            
-            if let Err(e) = insert_click_with_retry(db, &event).await {
+
+            let mut enrichment = enricher.enrich(&event).await;
+
+            // Without Redis the click is kept as a regular one rather than guessed to be a repeat
+            match deduper.is_duplicate(conn, &event, &enrichment).await {
+                Ok(is_duplicate) => enrichment.is_duplicate = is_duplicate,
+                Err(e) => tracing::warn!("Failed to check click dedupe window for slug {}: {:?}", event.slug, e),
+            }
+
+            if let Err(e) = insert_click_with_retry(db, &event, &enrichment).await {
                 tracing::error!("Failed to insert click after retries: {:?}", e);
             }
+
+            // Live subscribers are best effort; a missed publish only affects open dashboards
+            if let Err(e) = publish_live_click(conn, &event, &enrichment).await {
+                tracing::warn!("Failed to publish live click for slug {}: {:?}", event.slug, e);
+            }
         }
 
-        // Always acknowledge the message to prevent reprocessing
+        // Always acknowledge and delete the message to prevent reprocessing and keep no raw clicks around
         acknowledge_message(conn, stream_key, &stream_id.id).await;
     }
 }
@@ -116,29 +142,30 @@ fn parse_event_from_value(value: &redis::Value) -> Option<ClickEvent> {
                     match serde_json::from_str::<ClickEvent>(json_str) {
                         Ok(event) => Some(event),
                         Err(e) => {
-                            tracing::warn!("Failed to parse event: {:?}, error: {:?}", json_str, e);
+                            // The payload holds the raw IP and user agent, so it is not logged
+                            tracing::warn!("Failed to parse event: {:?}", e);
                             None
                         }
                     }
                 },
                 Err(_) => {
-                    tracing::warn!("Value is not valid UTF-8: {:?}", bytes);
+                    tracing::warn!("Value is not valid UTF-8 ({} bytes)", bytes.len());
                     None
                 }
             }
         },
         _ => {
-            tracing::warn!("Value is not a BulkString variant: {:?}", value);
+            tracing::warn!("Value is not a BulkString variant");
             None
         }
     }
 }
 
 async fn acknowledge_message(conn: &mut MultiplexedConnection, stream_key: &str, message_id: &str) {
-    let result: Result<(), redis::RedisError> = redis::cmd("XACK")
-        .arg(stream_key)
-        .arg(CONSUMER_GROUP)
-        .arg(message_id)
+    let result: Result<(), redis::RedisError> = redis::pipe()
+        .atomic()
+        .cmd("XACK").arg(stream_key).arg(CONSUMER_GROUP).arg(message_id).ignore()
+        .cmd("XDEL").arg(stream_key).arg(message_id).ignore()
         .query_async(conn)
         .await;
         
@@ -147,14 +174,36 @@ async fn acknowledge_message(conn: &mut MultiplexedConnection, stream_key: &str,
     }
 }
 
-pub async fn insert_click(db: &PgPool, click: &ClickEvent) -> Result<(), sqlx::Error> {
+pub async fn insert_click(db: &PgPool, click: &ClickEvent, enrichment: &ClickEnrichment) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        "INSERT INTO clicks (slug, ip, user_agent, referer, timestamp) VALUES ($1, $2, $3, $4, $5)",
+        "INSERT INTO clicks (slug, ip, user_agent, referer, timestamp, alias, browser_family, browser_version, os_family, device_type,
+                             referrer_domain, channel, country, region, city, asn, asn_org, is_bot, bot_reason, bot_name,
+                             visitor_id, is_duplicate, is_returning, click_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24)",
         click.slug,
-        click.ip,
+        enrichment.ip,
         click.user_agent,
         click.referer,
-        click.timestamp
+        click.timestamp,
+        click.alias,
+        enrichment.browser_family,
+        enrichment.browser_version,
+        enrichment.os_family,
+        enrichment.device_type,
+        enrichment.referrer_domain,
+        enrichment.channel,
+        enrichment.country,
+        enrichment.region,
+        enrichment.city,
+        enrichment.asn,
+        enrichment.asn_org,
+        enrichment.is_bot,
+        enrichment.bot_reason,
+        enrichment.bot_name,
+        enrichment.visitor_id,
+        enrichment.is_duplicate,
+        enrichment.is_returning,
+        click.click_id
     )
     .execute(db)
     .await?;
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use redis::{aio::MultiplexedConnection, ExistenceCheck, SetExpiry, SetOptions};
+use sha2::{Digest, Sha256};
+
+use crate::{config::Config, models::click::{ClickEnrichment, ClickEvent}};
+
+const KEY_PREFIX: &str = "click_dedupe";
+
+// Flags repeat clicks (double-clicks, browser retries) by one visitor on one link
+pub struct ClickDeduper {
+    window: Duration,
+}
+
+impl ClickDeduper {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            window: config.click_dedupe_window,
+        }
+    }
+
+    // The first click claims the window with SET NX, so concurrent consumers cannot both take
+    // the same click as the first. The claim expires a window after the click was made, not
+    // after it was processed, so a backlog replayed faster than it was clicked is still judged
+    // by click time; clicks that reach the consumer a whole window late are never flagged.
+    pub async fn is_duplicate(
+        &self,
+        conn: &mut MultiplexedConnection,
+        event: &ClickEvent,
+        enrichment: &ClickEnrichment,
+    ) -> redis::RedisResult<bool> {
+        let window_ms = self.window.as_millis() as u64;
+        if window_ms == 0 {
+            return Ok(false);
+        }
+
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::PXAT(window_end_ms(event, window_ms)));
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(dedupe_key(event, enrichment.visitor_id.as_deref()))
+            .arg(1)
+            .arg(options)
+            .query_async(conn)
+            .await?;
+
+        Ok(claimed.is_none())
+    }
+}
+
+fn dedupe_key(event: &ClickEvent, visitor_id: Option<&str>) -> String {
+    format!("{}:{}:{}", KEY_PREFIX, event.slug, fingerprint(event, visitor_id))
+}
+
+// The daily visitor hash, or a hash of IP and user agent when it could not be computed
+fn fingerprint(event: &ClickEvent, visitor_id: Option<&str>) -> String {
+    match visitor_id {
+        Some(visitor_id) => visitor_id.to_string(),
+        None => hex::encode(Sha256::digest(format!("{}|{}", event.ip, event.user_agent))),
+    }
+}
+
+fn window_end_ms(event: &ClickEvent, window_ms: u64) -> u64 {
+    (event.timestamp.timestamp_millis().max(0) as u64).saturating_add(window_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn click(slug: &str, ip: &str, user_agent: &str) -> ClickEvent {
+        ClickEvent {
+            slug: slug.to_string(),
+            ip: ip.to_string(),
+            user_agent: user_agent.to_string(),
+            referer: None,
+            timestamp: chrono::Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap(),
+            alias: None,
+            purpose: None,
+            accept_language: None,
+            visitor_cookie: None,
+            visitor_cookie_issued: false,
+            click_id: None,
+        }
+    }
+
+    #[test]
+    fn visitor_hash_is_used_when_available() {
+        let event = click("abc123", "203.0.113.7", "Firefox");
+        assert_eq!(dedupe_key(&event, Some("visitor")), "click_dedupe:abc123:visitor");
+    }
+
+    #[test]
+    fn ip_and_user_agent_are_hashed_without_a_visitor_hash() {
+        let event = click("abc123", "203.0.113.7", "Firefox");
+        let key = dedupe_key(&event, None);
+
+        assert!(key.starts_with("click_dedupe:abc123:"));
+        assert!(!key.contains("203.0.113.7"));
+        assert_eq!(key, dedupe_key(&click("abc123", "203.0.113.7", "Firefox"), None));
+        assert_ne!(key, dedupe_key(&click("abc123", "203.0.113.8", "Firefox"), None));
+        assert_ne!(key, dedupe_key(&click("abc123", "203.0.113.7", "Chrome"), None));
+    }
+
+    #[test]
+    fn keys_are_per_link() {
+        assert_ne!(
+            dedupe_key(&click("abc123", "203.0.113.7", "Firefox"), Some("visitor")),
+            dedupe_key(&click("xyz789", "203.0.113.7", "Firefox"), Some("visitor"))
+        );
+    }
+
+    #[test]
+    fn window_ends_relative_to_the_click() {
+        let event = click("abc123", "203.0.113.7", "Firefox");
+        assert_eq!(
+            window_end_ms(&event, 5_000),
+            event.timestamp.timestamp_millis() as u64 + 5_000
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use futures::{Stream, StreamExt};
+use redis::aio::MultiplexedConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{models::click::{ClickEnrichment, ClickEvent}, streams::get_redis_pubsub};
+
+// Enriched clicks are fanned out per slug, so every instance can serve any live connection
+const LIVE_CHANNEL_PREFIX: &str = "click_events:live";
+
+// What live subscribers see of a click; the IP and raw user agent are left out
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LiveClick {
+    pub slug: String,
+    pub alias: Option<String>,
+    pub timestamp: String,
+    pub referrer_domain: Option<String>,
+    pub channel: String,
+    pub country: Option<String>,
+    pub device_type: String,
+    pub browser_family: String,
+    pub is_bot: bool,
+    pub is_duplicate: bool,
+}
+
+fn live_channel(slug: &str) -> String {
+    format!("{}:{}", LIVE_CHANNEL_PREFIX, slug)
+}
+
+pub async fn publish_live_click(
+    conn: &mut MultiplexedConnection,
+    click: &ClickEvent,
+    enrichment: &ClickEnrichment,
+) -> redis::RedisResult<()> {
+    let live_click = LiveClick {
+        slug: click.slug.clone(),
+        alias: click.alias.clone(),
+        timestamp: click.timestamp.to_rfc3339(),
+        referrer_domain: enrichment.referrer_domain.clone(),
+        channel: enrichment.channel.clone(),
+        country: enrichment.country.clone(),
+        device_type: enrichment.device_type.clone(),
+        browser_family: enrichment.browser_family.clone(),
+        is_bot: enrichment.is_bot,
+        is_duplicate: enrichment.is_duplicate,
+    };
+
+    let payload = serde_json::to_string(&live_click)
+        .map_err(|e| redis::RedisError::from((redis::ErrorKind::TypeError, "Serialization error", e.to_string())))?;
+
+    redis::cmd("PUBLISH")
+        .arg(live_channel(&click.slug))
+        .arg(payload)
+        .query_async(conn)
+        .await
+}
+
+// Clicks on one primary slug as they are ingested; the subscription ends with the stream
+pub async fn subscribe_live_clicks(slug: &str) -> redis::RedisResult<impl Stream<Item = LiveClick>> {
+    let mut pubsub = get_redis_pubsub().await?;
+    pubsub.subscribe(live_channel(slug)).await?;
+
+    Ok(pubsub.into_on_message().filter_map(|message| async move {
+        let payload: String = message.get_payload().ok()?;
+        match serde_json::from_str::<LiveClick>(&payload) {
+            Ok(live_click) => Some(live_click),
+            Err(e) => {
+                tracing::warn!("Failed to parse live click: {:?}, error: {:?}", payload, e);
+                None
+            }
+        }
+    }))
+}
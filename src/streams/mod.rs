@@ -1,9 +1,13 @@
 pub mod producer;
 pub mod consumer;
 pub mod retry;
+pub mod live;
+pub mod dedupe;
+
+const REDIS_URL: &str = "redis://127.0.0.1/";
 
 pub async fn get_redis_conn() -> redis::RedisResult<redis::aio::MultiplexedConnection> {
-    let client = redis::Client::open("redis://127.0.0.1/")?;
+    let client = redis::Client::open(REDIS_URL)?;
     client
         .get_multiplexed_tokio_connection()
         .await
@@ -12,3 +16,12 @@ pub async fn get_redis_conn() -> redis::RedisResult<redis::aio::MultiplexedConne
             redis::RedisError::from((redis::ErrorKind::IoError, "Multiplexed connection failed"))
         })
 }
+
+// Dedicated connection for SUBSCRIBE, which cannot share a multiplexed connection
+pub async fn get_redis_pubsub() -> redis::RedisResult<redis::aio::PubSub> {
+    let client = redis::Client::open(REDIS_URL)?;
+    client.get_async_pubsub().await.map_err(|err| {
+        tracing::error!("Redis pub/sub connection error: {:?}", err);
+        err
+    })
+}
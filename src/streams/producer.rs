@@ -1,30 +1,40 @@
-use redis::AsyncCommands;
+use redis::{aio::MultiplexedConnection, streams::StreamMaxlen, AsyncCommands};
 use serde_json::json;
 
-use crate::{models::click::ClickEvent, streams::get_redis_conn};
+use crate::models::click::ClickEvent;
 
 
 pub const STREAM_KEY : &str = "click_events";
+// Events carry the raw IP and user agent, so the stream only holds clicks waiting to be stored:
+// the consumer deletes each entry once processed, and a stalled consumer leaves at most about
+// this many behind, dropping the oldest
+const STREAM_MAX_LEN: usize = 100_000;
 
-pub async fn publish_click_event(event : ClickEvent) -> redis::RedisResult<()>{
-    let mut conn = get_redis_conn().await?;
+pub async fn publish_click_event(conn: &mut MultiplexedConnection, event : ClickEvent) -> redis::RedisResult<()>{
     let payload = json!({
         "slug": event.slug,
         "ip": event.ip,
         "user_agent": event.user_agent,
         "referer": event.referer,
+        "alias": event.alias,
+        "purpose": event.purpose,
+        "accept_language": event.accept_language,
+        "visitor_cookie": event.visitor_cookie,
+        "visitor_cookie_issued": event.visitor_cookie_issued,
+        "click_id": event.click_id,
         "timestamp": event.timestamp.to_string()
     });
     
     let event_json = serde_json::to_string(&payload)
         .map_err(|e| redis::RedisError::from((redis::ErrorKind::TypeError, "Serialization error", e.to_string())))?;
-    let _ : String = conn
-        .xadd(
+    let id : String = conn
+        .xadd_maxlen(
             STREAM_KEY,
+            StreamMaxlen::Approx(STREAM_MAX_LEN),
             "*",
             &[("event", event_json.as_str())]
         ).await?;
 
-    tracing::info!("Published click event: {:?}", payload);
+    tracing::debug!("Published click event {} for slug {}", id, event.slug);
     Ok(())
 }
\ No newline at end of file
@@ -4,17 +4,17 @@ use rand::{rng, Rng};
 use sqlx::PgPool;
 use tokio::time::sleep;
 
-use crate::{models::click::ClickEvent, streams::consumer::insert_click};
+use crate::{models::click::{ClickEnrichment, ClickEvent}, streams::consumer::insert_click};
 
 pub const RETRY_DELAY_MS: u64 = 500;
 const MAX_RETRIES: u32 = 3;
 
-pub async fn insert_click_with_retry(db: &PgPool, click: &ClickEvent) -> Result<(), sqlx::Error> {
+pub async fn insert_click_with_retry(db: &PgPool, click: &ClickEvent, enrichment: &ClickEnrichment) -> Result<(), sqlx::Error> {
     let mut attempts = 0;
     let mut last_error = None;
 
     while attempts < MAX_RETRIES {
-        match insert_click(db, click).await {
+        match insert_click(db, click, enrichment).await {
             Ok(_) => return Ok(()),
             Err(e) => {
                 attempts += 1;
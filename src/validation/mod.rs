@@ -1 +1,3 @@
-pub mod url;
\ No newline at end of file
+pub mod url;
+pub mod tags;
+pub mod slug;
\ No newline at end of file
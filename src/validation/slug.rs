@@ -0,0 +1,32 @@
+use validator::ValidationError;
+
+// Top-level paths served by the API's own routes. A slug or alias with one of these names
+// would never reach the redirect, so they cannot be claimed.
+pub const RESERVED_SLUGS: &[&str] = &["campaigns", "conversions", "convert", "links", "shorten"];
+
+pub fn validate_slug_name(slug: &str) -> Result<(), ValidationError> {
+    if RESERVED_SLUGS.contains(&slug) {
+        return Err(ValidationError::new("reserved_slug"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_names_are_reserved() {
+        for slug in RESERVED_SLUGS {
+            assert!(validate_slug_name(slug).is_err());
+        }
+    }
+
+    #[test]
+    fn other_names_are_allowed() {
+        assert!(validate_slug_name("launch").is_ok());
+        assert!(validate_slug_name("my-links").is_ok());
+        // Routes are matched case-sensitively, so only the exact path is taken
+        assert!(validate_slug_name("Links").is_ok());
+    }
+}
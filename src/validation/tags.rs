@@ -0,0 +1,67 @@
+pub const MAX_TAGS: usize = 10;
+const MAX_TAG_LENGTH: usize = 32;
+
+pub fn validate_tags(tags: &[String]) -> Result<(), validator::ValidationError> {
+    if tags.len() > MAX_TAGS {
+        return Err(validator::ValidationError::new("too_many_tags"));
+    }
+
+    let valid = tags.iter().all(|tag| {
+        let tag = tag.trim();
+        !tag.is_empty()
+            && tag.len() <= MAX_TAG_LENGTH
+            && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("invalid_tag"))
+    }
+}
+
+// Tags are matched case-insensitively, so store them lowercased and deduplicated
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = tags
+        .into_iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_tags_lowercases_trims_sorts_and_dedups() {
+        let normalized = normalize_tags(tags(&[" Summer ", "launch", "SUMMER", "ads"]));
+        assert_eq!(normalized, tags(&["ads", "launch", "summer"]));
+    }
+
+    #[test]
+    fn normalize_tags_keeps_an_empty_list_empty() {
+        assert!(normalize_tags(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn validate_tags_rejects_bad_characters_and_lengths() {
+        assert!(validate_tags(&tags(&["spring-sale", "q3_2026"])).is_ok());
+        assert!(validate_tags(&tags(&["has space"])).is_err());
+        assert!(validate_tags(&tags(&["   "])).is_err());
+        assert!(validate_tags(&tags(&[&"x".repeat(MAX_TAG_LENGTH + 1)])).is_err());
+    }
+
+    #[test]
+    fn validate_tags_limits_the_count() {
+        let many: Vec<String> = (0..=MAX_TAGS).map(|i| format!("tag{}", i)).collect();
+        assert!(validate_tags(&many).is_err());
+        assert!(validate_tags(&many[..MAX_TAGS]).is_ok());
+    }
+}